@@ -15,6 +15,8 @@
  */
 
 use core::mem;
+use core::slice;
+use core::str;
 
 use crate::memiter::*;
 use crate::types::*;
@@ -40,6 +42,8 @@ struct CpioHeader {
 
 pub struct CpioResult {
     name: *const u8,
+    /// Length of `name`, without the NULL terminator.
+    name_len: usize,
     contents: *const u8,
     size: usize,
 }
@@ -66,6 +70,7 @@ pub fn parse_cpio(it: &mut MemIter) -> Option<CpioResult> {
 
     Some(CpioResult {
         name,
+        name_len: (header.namesize as usize).saturating_sub(1),
         contents,
         size: contents_len,
     })
@@ -77,7 +82,8 @@ pub fn find_file_memiter(cpio: &MemIter, filename: &MemIter) -> Option<MemIter>
     let mut iter = cpio.clone();
 
     while let Some(result) = parse_cpio(&mut iter) {
-        if unsafe { filename.iseq(result.name) } {
+        let name = unsafe { slice::from_raw_parts(result.name, result.name_len) };
+        if str::from_utf8(name).map_or(false, |name| filename.matches(name)) {
             return Some(unsafe { MemIter::from_raw(result.contents, result.size) });
         }
     }
@@ -15,11 +15,13 @@
  */
 
 use core::convert::{TryFrom, TryInto};
+use core::iter;
 use core::mem;
 use core::ptr;
 use core::slice;
 use core::str;
 
+use crate::memiter::*;
 use crate::std::*;
 
 use scopeguard::guard;
@@ -36,6 +38,8 @@ pub struct fdt_node {
     begin: *const u8,
     end: *const u8,
     strs: *const u8,
+    /// Depth of the node, as in `FdtNode`, so that it survives a round trip through C.
+    depth: usize,
 }
 
 #[derive(Clone)]
@@ -43,19 +47,21 @@ pub struct FdtNode<'a> {
     hdr: &'a FdtHeader,
     data: &'a [u8],
     strs: &'a [u8],
+    /// Number of nodes entered from the beginning of the struct block to reach this node.
+    depth: usize,
 }
 
 impl<'a> From<fdt_node> for FdtNode<'a> {
     fn from(n: fdt_node) -> Self {
         unsafe {
             let hdr = &*n.hdr;
-            let data_size = u32::from_be(hdr.size_dt_struct) as usize;
             let strs_size = u32::from_be(hdr.size_dt_strings) as usize;
 
             Self {
                 hdr,
-                data: slice::from_raw_parts(n.begin, data_size),
+                data: slice::from_raw_parts(n.begin, n.end.offset_from(n.begin) as usize),
                 strs: slice::from_raw_parts(n.strs, strs_size),
+                depth: n.depth,
             }
         }
     }
@@ -68,6 +74,7 @@ impl<'a> From<FdtNode<'a>> for fdt_node {
             begin: n.data.as_ptr(),
             end: unsafe { n.data.as_ptr().add(n.data.len()) },
             strs: n.strs.as_ptr(),
+            depth: n.depth,
         }
     }
 }
@@ -164,10 +171,10 @@ impl<'a> FdtTokenizer<'a> {
 
     fn align(&mut self) {
         let modular = self.cur.as_ptr() as usize % FDT_TOKEN_ALIGNMENT;
-        let (_, cur) = self
-            .cur
-            .split_at((FDT_TOKEN_ALIGNMENT - modular) % FDT_TOKEN_ALIGNMENT);
-        self.cur = cur;
+        let padding = (FDT_TOKEN_ALIGNMENT - modular) % FDT_TOKEN_ALIGNMENT;
+
+        // Data may end flush against the end of the block, leaving no room for padding.
+        self.cur = self.cur.get(padding..).unwrap_or(&[]);
     }
 
     fn bytes(&mut self, size: usize) -> Option<&'a [u8]> {
@@ -189,7 +196,12 @@ impl<'a> FdtTokenizer<'a> {
 
     fn token(&mut self) -> Option<FdtToken> {
         while let Some(v) = self.u32() {
-            let token = v.try_into().unwrap();
+            // An unknown token means the reader is not where a token was written, e.g., because
+            // the one before was misaligned.
+            let token = some_or!(v.try_into().ok(), {
+                self.collapse();
+                return None;
+            });
             if token != FdtToken::Nop {
                 return Some(token);
             }
@@ -242,10 +254,22 @@ impl<'a> FdtTokenizer<'a> {
 
         let size = this.u32()? as usize;
         let nameoff = this.u32()? as usize;
+        let value = this.cur;
         let buf = this.bytes(size)?;
 
-        //TODO: Need to verify the strings.
-        let name = this.strs[nameoff..].as_ptr();
+        // The value is padded with zeros up to the next token. Anything else means that the next
+        // token was not written at an aligned offset.
+        let padding = &value[size..value.len() - this.cur.len()];
+        if padding.iter().any(|&b| b != 0) {
+            return None;
+        }
+
+        // The name must be a NULL-terminated string within the strings block.
+        let name = this.strs.get(nameoff..)?;
+        if !name.contains(&b'\0') {
+            return None;
+        }
+        let name = name.as_ptr();
 
         mem::forget(this);
         Some((name, buf))
@@ -310,15 +334,33 @@ impl<'a> FdtNode<'a> {
         let strs_begin = u32::from_be(hdr.off_dt_strings) as usize;
         let strs_size = u32::from_be(hdr.size_dt_strings) as usize;
 
-        // TODO: Verify that it is all within the fdt.
-        // TODO: Verify strings as well.
+        // The tokenizer pads to absolute addresses, so property data is only aligned within the
+        // struct block if the block itself starts at an aligned offset.
+        if !is_aligned(data_begin, FDT_TOKEN_ALIGNMENT) {
+            return None;
+        }
+
+        // Check that both blocks lie within the blob, without letting the offsets wrap around.
+        let total_size = u32::from_be(hdr.totalsize) as usize;
+        if data_begin.checked_add(data_size)? > total_size
+            || strs_begin.checked_add(strs_size)? > total_size
+        {
+            return None;
+        }
+
         Some(FdtNode {
             hdr,
             data: unsafe { slice::from_raw_parts(hdr_ptr.add(data_begin), data_size) },
             strs: unsafe { slice::from_raw_parts(hdr_ptr.add(strs_begin), strs_size) },
+            depth: 0,
         })
     }
 
+    /// Returns the depth of this node. The root node is at depth 1.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
     pub fn read_property(&self, name: *const u8) -> Result<&'a [u8], ()> {
         let mut t = FdtTokenizer::new(self.data, self.strs);
         while let Some((prop_name, buf)) = t.next_property() {
@@ -330,25 +372,135 @@ impl<'a> FdtNode<'a> {
         Err(())
     }
 
-    pub fn first_child(&mut self) -> Option<&'a [u8]> {
+    /// Returns the total size of the values of this node's properties.
+    pub fn properties_size(&self) -> usize {
+        let mut t = FdtTokenizer::new(self.data, self.strs);
+        let mut size = 0;
+        while let Some((_, buf)) = t.next_property() {
+            size += buf.len();
+        }
+
+        size
+    }
+
+    /// Returns the names and values of this node's properties, in order. Names exclude their NULL
+    /// terminators.
+    pub fn properties(&self) -> impl Iterator<Item = (&'a [u8], &'a [u8])> {
         let mut t = FdtTokenizer::new(self.data, self.strs);
 
+        iter::from_fn(move || {
+            let (name, buf) = t.next_property()?;
+
+            // `next_property` checked that the name is terminated within the strings block.
+            let len = (0..).take_while(|&i| unsafe { *name.add(i) } != 0).count();
+            Some((unsafe { slice::from_raw_parts(name, len) }, buf))
+        })
+    }
+
+    /// Returns the names of the children of this node, including their NULL terminators.
+    pub fn child_names(&self) -> impl Iterator<Item = &'a [u8]> {
+        let mut t = FdtTokenizer::new(self.data, self.strs);
         t.skip_properties();
 
-        let child_name = t.next_subnode()?;
-        self.data = t.cur;
+        iter::from_fn(move || {
+            let name = t.next_subnode()?;
+            t.skip_node()?;
+            Some(name)
+        })
+    }
 
-        Some(child_name)
+    /// Returns the first child of this node, if any.
+    pub fn first_child(&self) -> Option<Self> {
+        let mut t = FdtTokenizer::new(self.data, self.strs);
+
+        t.skip_properties();
+        t.next_subnode()?;
+
+        Some(Self {
+            hdr: self.hdr,
+            data: t.cur,
+            strs: self.strs,
+            depth: self.depth + 1,
+        })
     }
 
-    pub fn next_sibling(&mut self) -> Option<&'a [u8]> {
+    /// Returns the node following this one under the same parent, if any.
+    pub fn next_sibling(&self) -> Option<Self> {
         let mut t = FdtTokenizer::new(self.data, self.strs);
 
         t.skip_node()?;
-        let sibling_name = t.next_subnode()?;
-        self.data = t.cur;
+        t.next_subnode()?;
 
-        Some(sibling_name)
+        Some(Self {
+            hdr: self.hdr,
+            data: t.cur,
+            strs: self.strs,
+            depth: self.depth,
+        })
+    }
+
+    /// Finds a node below this one whose `phandle` property is `phandle`, and returns its name
+    /// along with the node itself.
+    pub fn find_phandle(&self, phandle: u32) -> Option<(&'a [u8], Self)> {
+        let mut t = FdtTokenizer::new(self.data, self.strs);
+        let mut depth = 0;
+        t.skip_properties();
+
+        loop {
+            if let Some(name) = t.next_subnode() {
+                depth += 1;
+                let data = t.cur;
+                while let Some((prop_name, buf)) = t.next_property() {
+                    if unsafe { strcmp(prop_name, "phandle\0".as_ptr()) } == 0
+                        && buf == phandle.to_be_bytes()
+                    {
+                        let node = Self {
+                            hdr: self.hdr,
+                            data,
+                            strs: self.strs,
+                            depth: self.depth + depth,
+                        };
+                        return Some((name, node));
+                    }
+                }
+            } else {
+                // Stop at the end of this node.
+                if t.token()? != FdtToken::EndNode || depth == 0 {
+                    return None;
+                }
+                depth -= 1;
+            }
+        }
+    }
+
+    /// Checks that the begin- and end-node tokens from this node to the end of the structure block
+    /// are balanced, i.e., that the tree closes exactly where the `FDT_END` token is. In
+    /// particular, no second root node may follow the first one.
+    pub fn check_balanced(&self) -> Option<()> {
+        let mut t = FdtTokenizer::new(self.data, self.strs);
+        let mut depth = self.depth;
+
+        loop {
+            t.skip_properties();
+            if t.next_subnode().is_some() {
+                depth += 1;
+                continue;
+            }
+
+            match t.token()? {
+                FdtToken::EndNode => {
+                    depth = depth.checked_sub(1)?;
+                    if depth == 0 {
+                        return match t.token()? {
+                            FdtToken::End => Some(()),
+                            _ => None,
+                        };
+                    }
+                }
+                FdtToken::End if depth == 0 => return Some(()),
+                _ => return None,
+            }
+        }
     }
 
     pub fn find_child(&mut self, child: *const u8) -> Option<()> {
@@ -358,6 +510,27 @@ impl<'a> FdtNode<'a> {
         while let Some(name) = t.next_subnode() {
             if unsafe { strcmp(name.as_ptr(), child) } == 0 {
                 self.data = t.cur;
+                self.depth += 1;
+                return Some(());
+            }
+
+            t.skip_node();
+        }
+
+        None
+    }
+
+    /// Like `find_child`, but compares node names ignoring ASCII case.
+    pub fn find_child_ci(&mut self, child: &str) -> Option<()> {
+        let mut t = FdtTokenizer::new(self.data, self.strs);
+        t.skip_properties();
+
+        while let Some(name) = t.next_subnode() {
+            // Leave the NULL terminator out of the comparison.
+            let it = unsafe { MemIter::from_raw(name.as_ptr(), name.len() - 1) };
+            if it.eq_ignore_case(child) {
+                self.data = t.cur;
+                self.depth += 1;
                 return Some(());
             }
 
@@ -461,6 +634,12 @@ impl FdtHeader {
     pub fn total_size(&self) -> u32 {
         u32::from_be(self.totalsize)
     }
+
+    /// Returns whether the header carries the FDT magic number but is only compatible with newer
+    /// versions of the format than this reader supports.
+    pub fn is_newer_version(&self) -> bool {
+        self.magic == u32::from_be(FDT_MAGIC) && u32::from_be(self.last_comp_version) > FDT_VERSION
+    }
 }
 
 #[no_mangle]
@@ -93,30 +93,22 @@ impl<'a> FdtNode<'a> {
             .map(|size| size as usize * mem::size_of::<u32>())
             .unwrap_or(mem::size_of::<u32>());
 
-        node.first_child()?;
+        let mut next = Some(node.first_child()?);
 
         // TODO(HfO2): this loop was do-while in C. Make an interator for this.
-        loop {
+        while let Some(node) = next {
+            next = node.next_sibling();
+
             if node
                 .read_property("device_type\0".as_ptr())
                 .ok()
                 .filter(|data| *data == "cpu\0".as_bytes())
                 .is_none()
             {
-                if node.next_sibling().is_none() {
-                    break;
-                } else {
-                    continue;
-                }
+                continue;
             }
 
-            let mut data = ok_or!(node.read_property("reg\0".as_ptr()), {
-                if node.next_sibling().is_none() {
-                    break;
-                } else {
-                    continue;
-                }
-            });
+            let mut data = ok_or!(node.read_property("reg\0".as_ptr()), continue);
 
             // Get all entries for this CPU.
             while data.len() as usize >= address_size {
@@ -133,17 +125,13 @@ impl<'a> FdtNode<'a> {
 
                 data = &data[address_size..];
             }
-
-            if node.next_sibling().is_none() {
-                break;
-            }
         }
 
         Some(cpu_count)
     }
 
     pub fn find_memory_ranges(&self, p: &mut BootParams) -> Option<()> {
-        let mut node = self.clone();
+        let node = self;
 
         // Get the sizes of memory range addresses and sizes.
         let address_size = node
@@ -159,30 +147,22 @@ impl<'a> FdtNode<'a> {
         let entry_size = address_size + size_size;
 
         // Look for nodes with the device_type set to "memory".
-        node.first_child()?;
+        let mut next = Some(node.first_child()?);
         let mut mem_range_index = 0;
 
         // TODO(HfO2): this loop was do-while in C. Make an interator for this.
-        loop {
+        while let Some(node) = next {
+            next = node.next_sibling();
+
             if node
                 .read_property("device_type\0".as_ptr())
                 .ok()
                 .filter(|data| *data == "memory\0".as_bytes())
                 .is_none()
             {
-                if node.next_sibling().is_none() {
-                    break;
-                } else {
-                    continue;
-                }
+                continue;
             }
-            let mut data = ok_or!(node.read_property("reg\0".as_ptr()), {
-                if node.next_sibling().is_none() {
-                    break;
-                } else {
-                    continue;
-                }
-            });
+            let mut data = ok_or!(node.read_property("reg\0".as_ptr()), continue);
 
             // Traverse all memory ranges within this node.
             while data.len() >= entry_size {
@@ -200,10 +180,6 @@ impl<'a> FdtNode<'a> {
 
                 data = &data[entry_size..];
             }
-
-            if node.next_sibling().is_none() {
-                break;
-            }
         }
 
         p.mem_ranges_count = mem_range_index;
@@ -17,6 +17,7 @@
 #![no_std]
 #![feature(core_intrinsics)]
 #![feature(const_fn)]
+#![feature(const_if_match)]
 #![feature(const_panic)]
 #![feature(maybe_uninit_ref)]
 #![feature(ptr_offset_from)]
@@ -226,12 +226,17 @@ pub unsafe fn load_secondary(
         mem_range.end = pa_init(round_down(pa_addr(mem_range.end), PAGE_SIZE));
     }
 
-    for (i, manifest_vm) in manifest.vms.iter_mut().enumerate() {
-        let vm_id = HF_VM_ID_OFFSET + i as spci_vm_id_t;
+    for manifest_vm in manifest.vms.iter_mut() {
+        let vm_id = manifest_vm.vm_id;
         if vm_id == HF_PRIMARY_VM_ID {
             continue;
         }
 
+        if !manifest_vm.enabled {
+            dlog!("Skipping disabled VM{}.\n", vm_id);
+            continue;
+        }
+
         dlog!(
             "Loading VM{}: {}.\n",
             vm_id,
@@ -251,6 +256,16 @@ pub unsafe fn load_secondary(
             continue;
         });
 
+        // Only load as much of the image as the manifest asks for.
+        let kernel = match manifest_vm.kernel_size {
+            Some(size) if size > kernel.len() as u64 => {
+                dlog!("Kernel file is smaller than its declared size\n");
+                continue;
+            }
+            Some(size) => MemIter::from_raw(kernel.get_next(), size as usize),
+            None => kernel,
+        };
+
         let mem_size = round_up(manifest_vm.mem_size as usize, PAGE_SIZE) as u64;
         if mem_size < kernel.len() as u64 {
             dlog!("Kernel is larger than available memory\n");
@@ -16,21 +16,41 @@
 
 use core::convert::TryInto;
 use core::fmt::{self, Write};
+use core::hash::Hasher;
+use core::mem;
+use core::ops::Index;
+use core::ptr;
 
 use crate::fdt::*;
 use crate::memiter::*;
+use crate::page::*;
+use crate::std::*;
 use crate::types::*;
+use crate::utils::*;
 
 use arrayvec::ArrayVec;
 
-const VM_NAME_BUF_SIZE: usize = 2 + 5 + 1; // "vm" + number + null terminator
+/// Returns the number of decimal digits needed to print `n`.
+const fn decimal_digits(n: u64) -> usize {
+    if n < 10 {
+        1
+    } else {
+        1 + decimal_digits(n / 10)
+    }
+}
+
+// "vm" + number + null terminator
+const VM_NAME_BUF_SIZE: usize = 2 + decimal_digits(spci_vm_id_t::max_value() as u64) + 1;
 const_assert!(MAX_VMS <= 99999);
+const_assert!(VM_NAME_BUF_SIZE <= MANIFEST_MAX_STRING_LENGTH);
 
 #[derive(PartialEq, Debug)]
+#[non_exhaustive]
 pub enum Error {
     NoHypervisorFdtNode,
     NotCompatible,
-    ReservedVmId,
+    /// A VM node uses a reserved ID. Holds the offending ID.
+    ReservedVmId(spci_vm_id_t),
     NoPrimaryVm,
     TooManyVms,
     PropertyNotFound,
@@ -39,6 +59,49 @@ pub enum Error {
     MalformedStringList,
     MalformedInteger,
     IntegerOverflow,
+    UnexpectedProperty,
+    CorruptedFdt,
+    PropertyTooLarge,
+    TooManyReservedMemoryRegions,
+    /// Two memory regions overlap. Holds the IDs of the owning VMs, which are
+    /// `RESERVED_MEMORY_OWNER` for reserved memory.
+    MemoryRegionOverlap(spci_vm_id_t, spci_vm_id_t),
+    UnresolvedPhandle,
+    InvalidArgument,
+    TooManyMemoryRegions,
+    MisalignedAddress,
+    InconsistentSecurityState,
+    ConflictingProperties,
+    BufferTooSmall,
+    VmIdMismatch,
+    /// A VM requests more vCPUs than there are CPUs. Holds the ID of the VM.
+    VcpuCountExceedsCpus(spci_vm_id_t),
+    InvalidNotificationCount,
+    /// Two secondary VMs share a boot order. Holds the IDs of the VMs.
+    DuplicateBootOrder(spci_vm_id_t, spci_vm_id_t),
+    TooManyCompatibleStrings,
+    TooManyUuids,
+    TooManyInterrupts,
+    UnknownHypervisorChild,
+    ReservedProperty,
+    DuplicateDebugName,
+    /// Two partitions declare the same UUID. Holds the IDs of the VMs.
+    DuplicateUuid(spci_vm_id_t, spci_vm_id_t),
+    InvalidMemoryAttributes,
+    InsufficientMemory,
+    FdtTruncated,
+    UnresolvedKernel,
+    DuplicateInterrupt,
+    UnresolvedAlias,
+    TooManyAliases,
+    /// The affinity of a VM names a CPU which does not exist. Holds the ID of the VM.
+    InvalidCpuId(spci_vm_id_t),
+    UnsupportedFdtVersion,
+    CpuCountMismatch,
+    TooManyExecutionContexts,
+    InconsistentExecutionContexts,
+    /// The VMs have more vCPUs which may run on a CPU than the platform allows. Holds the CPU.
+    CpuOversubscribed(u32),
 }
 
 impl Into<&'static str> for Error {
@@ -47,7 +110,7 @@ impl Into<&'static str> for Error {
         match self {
             NoHypervisorFdtNode => "Could not find \"hypervisor\" node in manifest",
             NotCompatible => "Hypervisor manifest entry not compatible with Hafnium",
-            ReservedVmId => "Manifest defines a VM with a reserved ID",
+            ReservedVmId(..) => "Manifest defines a VM with a reserved ID",
             NoPrimaryVm => "Manifest does not contain a primary VM entry",
             TooManyVms => {
                 "Manifest specifies more VMs than Hafnium has statically allocated space for"
@@ -58,6 +121,82 @@ impl Into<&'static str> for Error {
             MalformedStringList => "Malformed string list property",
             MalformedInteger => "Malformed integer property",
             IntegerOverflow => "Integer overflow",
+            UnexpectedProperty => "Property not allowed for this VM",
+            CorruptedFdt => "Manifest FDT is corrupted",
+            PropertyTooLarge => "Property value too large",
+            TooManyReservedMemoryRegions => {
+                "Manifest specifies more reserved memory regions than Hafnium has space for"
+            }
+            MemoryRegionOverlap(..) => "Memory regions overlap",
+            UnresolvedPhandle => "Phandle does not refer to any node",
+            InvalidArgument => "Invalid argument",
+            TooManyMemoryRegions => {
+                "Manifest specifies more memory regions for a VM than Hafnium has space for"
+            }
+            MisalignedAddress => "Address is not page-aligned",
+            InconsistentSecurityState => {
+                "Exception level must be declared by secure partitions and only by them"
+            }
+            ConflictingProperties => "Conflicting or incomplete set of properties",
+            BufferTooSmall => "Buffer too small",
+            VmIdMismatch => "VM node name does not match its \"id\" property",
+            VcpuCountExceedsCpus(..) => "VM requests more vCPUs than there are CPUs",
+            InvalidNotificationCount => "Partition declares more notifications than supported",
+            DuplicateBootOrder(..) => "Two secondary VMs share the same boot order",
+            TooManyCompatibleStrings => {
+                "Hypervisor node has more \"compatible\" entries than Hafnium has space for"
+            }
+            TooManyUuids => "Partition declares more UUIDs than Hafnium has space for",
+            TooManyInterrupts => "VM declares more interrupts than Hafnium has space for",
+            UnknownHypervisorChild => "Hypervisor node has a child which is not a VM",
+            ReservedProperty => {
+                "VM node uses a property name reserved by the Devicetree specification"
+            }
+            DuplicateDebugName => "Two VMs share the same debug name",
+            DuplicateUuid(..) => "Two partitions declare the same UUID",
+            InvalidMemoryAttributes => {
+                "Memory region has reserved attribute bits set or is both writable and executable"
+            }
+            InsufficientMemory => "VMs need more memory than the platform has",
+            FdtTruncated => "Manifest FDT is shorter than its header says",
+            UnresolvedKernel => "Could not resolve the kernel filename of a VM",
+            DuplicateInterrupt => "VM declares the same interrupt with different attributes",
+            UnresolvedAlias => "Alias does not refer to any node",
+            TooManyAliases => "Manifest declares more VM aliases than Hafnium has space for",
+            InvalidCpuId(..) => "VM affinity names a CPU beyond the number of CPUs",
+            UnsupportedFdtVersion => "Manifest FDT requires a newer version of the format",
+            CpuCountMismatch => "Manifest was built for a platform with a different number of CPUs",
+            TooManyExecutionContexts => {
+                "Partition declares more execution contexts than Hafnium has space for"
+            }
+            InconsistentExecutionContexts => {
+                "Execution contexts do not add up to the declared execution context count"
+            }
+            CpuOversubscribed(..) => "More vCPUs may run on a CPU than the platform allows",
+        }
+    }
+}
+
+impl Error {
+    /// Returns whether the error means the FDT itself is corrupted or malformed, as opposed to a
+    /// well-formed manifest whose contents are rejected or exceed Hafnium's limits.
+    pub fn is_fatal(&self) -> bool {
+        use Error::*;
+        match self {
+            CorruptedFdt | FdtTruncated | MalformedString | MalformedStringList
+            | MalformedInteger | IntegerOverflow | PropertyTooLarge | UnresolvedPhandle
+            | UnresolvedAlias => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<MemIterError> for Error {
+    fn from(e: MemIterError) -> Self {
+        match e {
+            MemIterError::BufferTooSmall => Error::BufferTooSmall,
+            MemIterError::UnevenChunks | MemIterError::InvalidDigit => Error::MalformedInteger,
+            MemIterError::Overflow => Error::IntegerOverflow,
         }
     }
 }
@@ -65,22 +204,568 @@ impl Into<&'static str> for Error {
 /// Maximum length of a string parsed from the FDT, including NULL terminator.
 const MANIFEST_MAX_STRING_LENGTH: usize = 32;
 
+/// Maximum length of the `bootargs` of the `chosen` node, including NULL terminator.
+const MAX_BOOTARGS_LENGTH: usize = 256;
+
+/// Maximum size of the `kernel-boot-params` of a VM, in bytes.
+const MAX_KERNEL_BOOT_PARAMS_LENGTH: usize = 64;
+
+/// Maximum size of a property value read from the FDT, in bytes.
+const MAX_PROPERTY_LEN: usize = 1024;
+
+/// Maximum depth of FDT nodes accepted in the manifest. Bounds the recursion of `check_depth`.
+const MAX_FDT_DEPTH: usize = 16;
+
+/// Maximum number of regions parsed from the `reserved-memory` node.
+const MAX_RESERVED_MEMORY: usize = 8;
+
+/// Maximum number of aliases referring to VMs kept from the `aliases` node.
+const MAX_ALIASES: usize = 8;
+
+/// Maximum number of `ctx@N` execution contexts declared by a single composite partition.
+const MAX_EXECUTION_CONTEXTS: usize = 4;
+
+/// Maximum number of memory regions declared by a single VM.
+const MAX_MEMORY_REGIONS: usize = 4;
+
+/// Maximum number of notifications a partition can receive, i.e., the width of the bitmap.
+const MAX_NOTIFICATIONS: u32 = 64;
+
+/// Property names reserved by the Devicetree specification, which carry no manifest data.
+const RESERVED_PROPERTIES: [&str; 3] = ["name\0", "phandle\0", "linux,phandle\0"];
+
+/// Maximum number of interrupts declared by a single VM.
+const MAX_INTERRUPTS: usize = 8;
+
+/// Maximum number of UUIDs declared by a single partition.
+const MAX_UUIDS: usize = 4;
+
+/// Maximum number of entries in the `compatible` property of the hypervisor node.
+const MAX_COMPATIBLE: usize = 4;
+
+/// Capacity of the map returned by `Manifest::memory_map`: a boot region and the memory regions of
+/// every VM, plus the reserved memory.
+const MAX_MEMORY_MAP_ENTRIES: usize = 128;
+const_assert!(MAX_VMS * (MAX_MEMORY_REGIONS + 1) + MAX_RESERVED_MEMORY <= MAX_MEMORY_MAP_ENTRIES);
+
+/// Highest `log-level` a VM may request.
+pub const MAX_LOG_LEVEL: u8 = 4;
+
+/// Log level of VMs which do not request one.
+pub const DEFAULT_LOG_LEVEL: u8 = 2;
+
+/// `load_flags` bit requesting that the VM's images be signature-checked before booting it.
+pub const LOAD_FLAG_VERIFY_SIGNATURE: u32 = 0b01;
+
+/// `load_flags` bit requesting that the VM's images be decrypted while loading them.
+pub const LOAD_FLAG_DECRYPT: u32 = 0b10;
+
+/// `load_flags` bits with a defined meaning. The others are reserved.
+const LOAD_FLAGS_MASK: u32 = LOAD_FLAG_VERIFY_SIGNATURE | LOAD_FLAG_DECRYPT;
+
+/// `MemoryRegion::attributes` bit allowing the VM to read the region.
+pub const MEMORY_REGION_READ: u32 = 0b0001;
+
+/// `MemoryRegion::attributes` bit allowing the VM to write the region.
+pub const MEMORY_REGION_WRITE: u32 = 0b0010;
+
+/// `MemoryRegion::attributes` bit allowing the VM to execute from the region.
+pub const MEMORY_REGION_EXECUTE: u32 = 0b0100;
+
+/// `MemoryRegion::attributes` bit mapping the region as uncached device memory.
+pub const MEMORY_REGION_DEVICE: u32 = 0b1000;
+
+/// `MemoryRegion::attributes` bits with a defined meaning. The others are reserved.
+const MEMORY_REGION_ATTRIBUTES_MASK: u32 =
+    MEMORY_REGION_READ | MEMORY_REGION_WRITE | MEMORY_REGION_EXECUTE | MEMORY_REGION_DEVICE;
+
+/// VM IDs from this one up are assigned to secure partitions.
+const SECURE_VM_ID_BASE: spci_vm_id_t = 0x8000;
+
+/// Owner reported for reserved memory, as VM ID 0 stands for the hypervisor itself.
+pub const RESERVED_MEMORY_OWNER: spci_vm_id_t = 0;
+
 /// Holds information about one of the VMs described in the manifest.
-#[derive(Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ManifestVm {
+    pub vm_id: spci_vm_id_t,
+
     // Properties defined for both primary and secondary VMs.
     pub debug_name: [u8; MANIFEST_MAX_STRING_LENGTH],
+    /// Name of the node referred to by `interrupt-parent`, or by the entries of
+    /// `interrupts-extended`, or empty if there is none.
+    pub interrupt_controller: [u8; MANIFEST_MAX_STRING_LENGTH],
+    /// Interrupts of the VM, decoded from the `interrupts` or `interrupts-extended` property.
+    pub interrupts: ArrayVec<[Interrupt; MAX_INTERRUPTS]>,
+    /// Ramdisk handed to the VM, or empty if there is none.
+    pub ramdisk_filename: [u8; MANIFEST_MAX_STRING_LENGTH],
+    /// Opaque boot parameters handed to the VM's kernel.
+    pub kernel_boot_params: Option<ArrayVec<[u8; MAX_KERNEL_BOOT_PARAMS_LENGTH]>>,
+    pub memory_regions: ArrayVec<[MemoryRegion; MAX_MEMORY_REGIONS]>,
+    /// Service UUIDs exposed by the partition, each as four 32-bit words.
+    pub uuids: ArrayVec<[[u32; 4]; MAX_UUIDS]>,
+    /// Whether the VM is a secure partition rather than a normal world VM.
+    pub secure: bool,
+    /// Exception level a secure partition runs at. Always `None` for normal world VMs.
+    pub exception_level: Option<ExceptionLevel>,
+    /// Execution contexts of a composite partition. If there are any, `execution_ctx_count` must
+    /// be their total number of vCPUs.
+    pub execution_contexts: ArrayVec<[ExecutionContext; MAX_EXECUTION_CONTEXTS]>,
+    /// Declared total number of vCPUs of the `execution_contexts`.
+    pub execution_ctx_count: Option<spci_vcpu_count_t>,
+    /// Whether the VM is passed a boot information blob at boot.
+    pub boot_info: bool,
+    /// General-purpose register holding the address of the boot information blob.
+    pub gp_register: Option<u32>,
+    /// Whether the partition supports receiving notifications.
+    pub notification_support: bool,
+    /// Number of notifications the partition can receive.
+    pub notification_count: Option<u32>,
+    /// Scheduling quantum of the VM, or `None` to use the platform default.
+    pub time_slice_ms: Option<u32>,
+    /// Bitmask of the CPUs the VM's vCPUs may run on, or `None` if they may run on any.
+    pub cpu_affinity: Option<u64>,
+    /// Bitmask of the PSCI power states the partition tolerates in `CPU_SUSPEND`, or `None` if it
+    /// tolerates all of them.
+    pub psci_suspend_mask: Option<u64>,
+    /// Verbosity requested by the VM, from 0 to `MAX_LOG_LEVEL`.
+    pub log_level: u8,
+    /// `LOAD_FLAG_*` bits controlling how the VM's images are loaded.
+    pub load_flags: u32,
+    /// Whether the VM is booted. False if its `status` is "disabled".
+    pub enabled: bool,
+    /// What to do when the VM stops. `Never` if there is no `restart-policy`.
+    pub restart_policy: RestartPolicy,
+    /// Offset of the entry point from the start of the kernel image.
+    pub entry_offset: u64,
 
     // Properties specific to secondary VMs.
     pub kernel_filename: [u8; MANIFEST_MAX_STRING_LENGTH],
     pub mem_size: u64,
+    /// Number of bytes of the kernel image to load, or `None` to load the whole file.
+    pub kernel_size: Option<u64>,
+    pub vcpu_count: spci_vcpu_count_t,
+    pub watchdog_timeout_ms: Option<u32>,
+    /// Position of the VM in the boot sequence, or `None` if unspecified.
+    pub boot_order: Option<u16>,
+    /// Physical address the VM is loaded at, or `None` to let Hafnium choose.
+    pub boot_address: Option<u64>,
+}
+
+/// Compares the contents of the string fields, ignoring whatever follows the NULL terminator.
+impl PartialEq for ManifestVm {
+    fn eq(&self, other: &Self) -> bool {
+        self.vm_id == other.vm_id
+            && as_asciz(&self.debug_name) == as_asciz(&other.debug_name)
+            && as_asciz(&self.interrupt_controller) == as_asciz(&other.interrupt_controller)
+            && self.interrupts == other.interrupts
+            && as_asciz(&self.ramdisk_filename) == as_asciz(&other.ramdisk_filename)
+            && self.kernel_boot_params == other.kernel_boot_params
+            && self.memory_regions == other.memory_regions
+            && self.uuids == other.uuids
+            && self.secure == other.secure
+            && self.exception_level == other.exception_level
+            && self.execution_contexts == other.execution_contexts
+            && self.execution_ctx_count == other.execution_ctx_count
+            && self.boot_info == other.boot_info
+            && self.gp_register == other.gp_register
+            && self.notification_support == other.notification_support
+            && self.notification_count == other.notification_count
+            && self.time_slice_ms == other.time_slice_ms
+            && self.cpu_affinity == other.cpu_affinity
+            && self.psci_suspend_mask == other.psci_suspend_mask
+            && self.log_level == other.log_level
+            && self.load_flags == other.load_flags
+            && self.enabled == other.enabled
+            && self.restart_policy == other.restart_policy
+            && self.entry_offset == other.entry_offset
+            && as_asciz(&self.kernel_filename) == as_asciz(&other.kernel_filename)
+            && self.mem_size == other.mem_size
+            && self.kernel_size == other.kernel_size
+            && self.vcpu_count == other.vcpu_count
+            && self.watchdog_timeout_ms == other.watchdog_timeout_ms
+            && self.boot_order == other.boot_order
+            && self.boot_address == other.boot_address
+    }
+}
+
+/// A region of memory described by a child of the top-level `reserved-memory` node.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReservedMemory {
+    pub base: u64,
+    pub size: u64,
+    /// The region must not be mapped by the operating system.
+    pub no_map: bool,
+    /// The operating system may use the region as long as it can be reclaimed.
+    pub reusable: bool,
+    /// Label given to the region by the node's `reg-names`, or empty if there is none.
+    pub name: [u8; MANIFEST_MAX_STRING_LENGTH],
+}
+
+impl ReservedMemory {
+    fn overlaps(&self, other: &Self) -> bool {
+        self.base < other.base + other.size && other.base < self.base + self.size
+    }
+}
+
+/// An interrupt of a VM.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Interrupt {
+    pub id: u32,
+    pub attributes: u32,
+}
+
+impl Interrupt {
+    /// Decodes an interrupt specifier of `#interrupt-cells` cells. A single cell is the ID, two
+    /// cells are the ID and attributes, and three cells follow the GIC binding of type, number and
+    /// flags.
+    fn decode(cells: &[u8]) -> Result<Self, Error> {
+        let mut words = cells
+            .chunks(mem::size_of::<u32>())
+            .map(|cell| u32::from_be_bytes(cell.try_into().unwrap()));
+        let mut next = || words.next().unwrap();
+
+        match cells.len() / mem::size_of::<u32>() {
+            1 => Ok(Self {
+                id: next(),
+                attributes: 0,
+            }),
+            2 => Ok(Self {
+                id: next(),
+                attributes: next(),
+            }),
+            3 => {
+                // SPIs are numbered from 32 and PPIs from 16.
+                let offset = match next() {
+                    0 => 32,
+                    1 => 16,
+                    _ => return Err(Error::MalformedInteger),
+                };
+                let id = next().checked_add(offset).ok_or(Error::IntegerOverflow)?;
+                Ok(Self {
+                    id,
+                    attributes: next(),
+                })
+            }
+            _ => Err(Error::MalformedInteger),
+        }
+    }
+}
+
+/// Reads the `#address-cells` and `#size-cells` of `node`, which size the `reg` entries of its
+/// children. Absent counts default to the values given by the Devicetree specification, and
+/// addresses and sizes must fit in 64 bits.
+fn read_cell_counts<'a>(node: &FdtNode<'a>) -> Result<(usize, usize), Error> {
+    let address_cells = optional(node.read_u32("#address-cells\0".as_ptr()))?.unwrap_or(2) as usize;
+    let size_cells = optional(node.read_u32("#size-cells\0".as_ptr()))?.unwrap_or(1) as usize;
+    if address_cells < 1 || address_cells > 2 || size_cells < 1 || size_cells > 2 {
+        return Err(Error::MalformedInteger);
+    }
+
+    Ok((address_cells, size_cells))
+}
+
+/// Appends the interrupts decoded from the `interrupts` property of `node`, if it has one, given
+/// the `#interrupt-cells` of their controller.
+fn read_interrupts<'a>(
+    node: &FdtNode<'a>,
+    interrupt_cells: usize,
+    interrupts: &mut ArrayVec<[Interrupt; MAX_INTERRUPTS]>,
+) -> Result<(), Error> {
+    if let Some(data) = optional(node.read_property_checked("interrupts\0".as_ptr()))? {
+        let specifier_size = interrupt_cells * mem::size_of::<u32>();
+        if specifier_size == 0 || data.len() % specifier_size != 0 {
+            return Err(Error::MalformedInteger);
+        }
+
+        for specifier in data.chunks(specifier_size) {
+            interrupts
+                .try_push(Interrupt::decode(specifier)?)
+                .map_err(|_| Error::TooManyInterrupts)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Flat snapshot of a VM's manifest entry, for consumption from C.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VmSummary {
+    pub id: spci_vm_id_t,
+    pub vcpu_count: spci_vcpu_count_t,
+    pub mem_size: u64,
+}
+
+/// Resources of the platform a manifest is checked against by `Manifest::validate_all`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlatformLimits {
+    /// Bytes of memory available to VMs.
+    pub total_ram: u64,
+    /// Number of physical CPUs.
+    pub cpu_count: usize,
+    /// Number of vCPUs which may share a physical CPU, counted by `Manifest::total_vcpus_on_cpu`.
+    pub max_vcpus_per_cpu: u32,
+}
+
+/// Exception level of a secure partition.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExceptionLevel {
+    El0,
+    El1,
+}
+
+/// Whether a VM which stops is restarted, from its `restart-policy` property.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RestartPolicy {
+    /// "never", the default.
+    Never,
+    /// "on-failure", i.e., only if it crashed.
+    OnFailure,
+    /// "always".
+    Always,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::Never
+    }
+}
+
+/// Format of a VM's kernel image, as told by the extension of its filename.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KernelKind {
+    /// An ELF executable, named `*.elf`.
+    Elf,
+    /// A raw binary, named `*.bin` or anything not recognised otherwise.
+    Raw,
+    /// A Flattened Image Tree, named `*.itb`.
+    Fit,
+}
+
+/// An execution context of a composite partition, declared in a `ctx@N` child of its VM node.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ExecutionContext {
     pub vcpu_count: spci_vcpu_count_t,
+    pub exception_level: Option<ExceptionLevel>,
+}
+
+/// A region of memory declared in a child of a VM's `memory-regions` node.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MemoryRegion {
+    pub base_address: u64,
+    pub page_count: u32,
+    /// `MEMORY_REGION_*` bits giving the access permissions and memory type.
+    pub attributes: u32,
+    /// Whether the region was declared relative to the VM's `boot_address`. `base_address` is
+    /// absolute either way.
+    pub relative: bool,
+    /// Interrupts of the device in a `MEMORY_REGION_DEVICE` region, decoded with the
+    /// `#interrupt-cells` of the VM's `interrupt-parent`. Empty for other regions.
+    pub interrupts: ArrayVec<[Interrupt; MAX_INTERRUPTS]>,
+}
+
+impl MemoryRegion {
+    fn new<'a>(
+        node: &FdtNode<'a>,
+        boot_address: Option<u64>,
+        interrupt_cells: usize,
+    ) -> Result<Self, Error> {
+        let mut base_address = node.read_u64("base-address\0".as_ptr())?;
+        let relative = node.read_property("relative-address\0".as_ptr()).is_ok();
+        if relative {
+            base_address = boot_address
+                .ok_or(Error::ConflictingProperties)?
+                .checked_add(base_address)
+                .ok_or(Error::IntegerOverflow)?;
+        }
+
+        let mut interrupts = ArrayVec::new();
+        read_interrupts(node, interrupt_cells, &mut interrupts)?;
+
+        let region = Self {
+            base_address,
+            page_count: node.read_u32("pages-count\0".as_ptr())?,
+            attributes: node.read_u32("attributes\0".as_ptr())?,
+            relative,
+            interrupts,
+        };
+        region.validate()?;
+
+        Ok(region)
+    }
+
+    /// Checks that the region is page-aligned, does not wrap around and has valid attributes, and
+    /// that only device regions have interrupts.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !is_aligned(self.base_address as usize, PAGE_SIZE) {
+            return Err(Error::MisalignedAddress);
+        }
+
+        // Enforce W^X: a region may be writable or executable, but not both.
+        let write_execute = MEMORY_REGION_WRITE | MEMORY_REGION_EXECUTE;
+        if self.attributes & !MEMORY_REGION_ATTRIBUTES_MASK != 0
+            || self.attributes & write_execute == write_execute
+        {
+            return Err(Error::InvalidMemoryAttributes);
+        }
+
+        if self.size().checked_add(self.base_address).is_none() {
+            return Err(Error::IntegerOverflow);
+        }
+
+        if !self.interrupts.is_empty() && self.attributes & MEMORY_REGION_DEVICE == 0 {
+            return Err(Error::ConflictingProperties);
+        }
+
+        Ok(())
+    }
+
+    pub fn size(&self) -> u64 {
+        u64::from(self.page_count) * PAGE_SIZE as u64
+    }
+
+    /// Returns the address right after the end of the region.
+    pub fn end(&self) -> u64 {
+        self.base_address + self.size()
+    }
+}
+
+/// Statistics gathered while parsing the manifest.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct ParseStats {
+    /// Number of child nodes looked up by name.
+    pub nodes_visited: usize,
+    /// Total size of the property values of the parsed hypervisor and VM nodes.
+    pub bytes_read: usize,
+    /// Number of VM nodes parsed.
+    pub vms_parsed: usize,
+}
+
+impl ParseStats {
+    /// Counts a child node looked up by name.
+    fn visit_node(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.nodes_visited += 1;
+        }
+    }
+
+    /// Counts the property values of `node` as read.
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    fn read<'a>(&mut self, node: &FdtNode<'a>) {
+        #[cfg(feature = "stats")]
+        {
+            self.bytes_read += node.properties_size();
+        }
+    }
+
+    /// Counts the VM parsed from `node`, along with its property values.
+    fn vm<'a>(&mut self, node: &FdtNode<'a>) {
+        #[cfg(feature = "stats")]
+        {
+            self.vms_parsed += 1;
+        }
+        self.read(node);
+    }
+}
+
+/// Options controlling how strictly the manifest is parsed.
+#[derive(Default)]
+struct InitOptions<'a> {
+    /// Generate a "vmN" `debug_name` for VMs which do not declare one.
+    default_debug_name: bool,
+    /// Reject nodes and properties which would otherwise be ignored, and values which are
+    /// valid but almost certainly mistakes.
+    strict: bool,
+    /// Reserved VM IDs whose nodes are parsed as VMs rather than rejected.
+    allowed_reserved_ids: &'a [spci_vm_id_t],
 }
 
+/// IDs of the VMs left out by `Manifest::init_skip_malformed`, with the errors they failed with.
+pub type SkippedVms = ArrayVec<[(spci_vm_id_t, Error); MAX_VMS]>;
+
 /// Hafnium manifest parsed from FDT.
 #[derive(Debug)]
 pub struct Manifest {
     pub vms: ArrayVec<[ManifestVm; MAX_VMS]>,
+    pub reserved_memory: ArrayVec<[ReservedMemory; MAX_RESERVED_MEMORY]>,
+    /// Entries of the hypervisor node's `compatible` property, in order.
+    pub compatible: ArrayVec<[[u8; MANIFEST_MAX_STRING_LENGTH]; MAX_COMPATIBLE]>,
+    /// CPU frequency declared for timer setup, if any.
+    pub cpu_frequency_hz: Option<u64>,
+    /// Size of the hypervisor stack of each CPU, or `None` to use the built-in default.
+    pub stack_size: Option<u64>,
+    /// Oldest Hafnium version the manifest supports, if it says.
+    pub min_version: Option<u32>,
+    /// Newest Hafnium version the manifest supports, if it says.
+    pub max_version: Option<u32>,
+    /// Command line for the primary VM's kernel from the `chosen` node, without the NULL
+    /// terminator.
+    pub bootargs: Option<ArrayVec<[u8; MAX_BOOTARGS_LENGTH]>>,
+    /// Names from the top-level `aliases` node which refer to VM nodes, with the IDs of the VMs.
+    pub aliases: ArrayVec<[([u8; MANIFEST_MAX_STRING_LENGTH], spci_vm_id_t); MAX_ALIASES]>,
+    /// Checksum of the FDT blob last parsed by `reinit_if_changed`.
+    fdt_checksum: Option<u64>,
+    /// Work done by the last parse. Only counted with the `stats` feature.
+    stats: ParseStats,
+}
+
+/// Receives what `parse` reads from a manifest. A `Manifest` stores all of it, while a `VmCounter`
+/// keeps only what later parts of the manifest are checked against.
+trait ManifestSink {
+    /// Returns the statistics of the parse.
+    fn stats(&mut self) -> &mut ParseStats;
+
+    /// Returns the entries of the hypervisor node's `compatible` property read so far.
+    fn compatible(&mut self) -> &mut ArrayVec<[[u8; MANIFEST_MAX_STRING_LENGTH]; MAX_COMPATIBLE]>;
+
+    /// Takes the optional properties of the hypervisor node, once they are validated.
+    fn set_hypervisor_properties(
+        &mut self,
+        cpu_frequency_hz: Option<u64>,
+        stack_size: Option<u64>,
+        min_version: Option<u32>,
+        max_version: Option<u32>,
+    );
+
+    /// Returns a default VM for the next VM node to be parsed into, or `Error::TooManyVms` if no
+    /// more VMs can be kept.
+    fn vm_slot(&mut self) -> Result<&mut ManifestVm, Error>;
+
+    /// Keeps the VM parsed into the last slot, unless it conflicts with a VM kept before.
+    fn keep_vm(&mut self) -> Result<(), Error>;
+
+    /// Drops the VM parsed into the last slot.
+    fn discard_vm(&mut self);
+
+    /// Returns whether a VM with ID `vm_id` has been kept.
+    fn has_vm(&self, vm_id: spci_vm_id_t) -> bool;
+
+    /// Takes the `bootargs` of the `chosen` node, if there are any.
+    fn set_bootargs(&mut self, bootargs: ArrayVec<[u8; MAX_BOOTARGS_LENGTH]>);
+
+    /// Returns the aliases of VM nodes read so far.
+    fn aliases(
+        &mut self,
+    ) -> &mut ArrayVec<[([u8; MANIFEST_MAX_STRING_LENGTH], spci_vm_id_t); MAX_ALIASES]>;
+
+    /// Returns the reserved memory regions read so far.
+    fn reserved_memory(&mut self) -> &mut ArrayVec<[ReservedMemory; MAX_RESERVED_MEMORY]>;
+}
+
+/// Sink for `Manifest::check`, which parses every VM into the same scratch VM and keeps only its
+/// debug name and ID, rather than storing a whole manifest.
+#[derive(Default)]
+struct VmCounter {
+    stats: ParseStats,
+    compatible: ArrayVec<[[u8; MANIFEST_MAX_STRING_LENGTH]; MAX_COMPATIBLE]>,
+    aliases: ArrayVec<[([u8; MANIFEST_MAX_STRING_LENGTH], spci_vm_id_t); MAX_ALIASES]>,
+    reserved_memory: ArrayVec<[ReservedMemory; MAX_RESERVED_MEMORY]>,
+    /// VM being parsed.
+    vm: ManifestVm,
+    /// Debug name and ID of each VM kept so far.
+    kept: ArrayVec<[([u8; MANIFEST_MAX_STRING_LENGTH], spci_vm_id_t); MAX_VMS]>,
 }
 
 /// Generates a string with the two letters "vm" followed by an integer.
@@ -111,15 +796,110 @@ fn generate_vm_node_name<'a>(
     &mut buf.buf[..buf.size]
 }
 
+/// Validates the FDT header at the beginning of `fdt` and returns the root node. A blob cut short
+/// is reported as `Error::FdtTruncated`, one in a newer incompatible format as
+/// `Error::UnsupportedFdtVersion`, and any other inconsistency as `Error::CorruptedFdt`.
+fn fdt_root(fdt: &MemIter) -> Result<FdtNode, Error> {
+    if fdt.len() < mem::size_of::<FdtHeader>() {
+        return Err(Error::FdtTruncated);
+    }
+    if !is_aligned(fdt.get_next() as usize, mem::align_of::<FdtHeader>()) {
+        return Err(Error::CorruptedFdt);
+    }
+
+    #[allow(clippy::cast_ptr_alignment)]
+    let hdr = unsafe { &*(fdt.get_next() as *const FdtHeader) };
+    if (hdr.total_size() as usize) > fdt.len() {
+        return Err(Error::FdtTruncated);
+    }
+
+    if hdr.is_newer_version() {
+        return Err(Error::UnsupportedFdtVersion);
+    }
+
+    let mut root = FdtNode::new_root(hdr).ok_or(Error::CorruptedFdt)?;
+    root.find_child("\0".as_ptr()).ok_or(Error::CorruptedFdt)?;
+
+    Ok(root)
+}
+
+/// 64-bit FNV-1a hasher, used to detect changes to an FDT blob.
+struct Fnv1aHasher(u64);
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Checks whether `name`, including its NULL terminator, has the form "vmN" used for VM nodes.
+fn is_vm_node_name(name: &[u8]) -> bool {
+    let name = as_asciz(name);
+    if name.len() < 3 || &name[..2] != b"vm" {
+        return false;
+    }
+
+    // Node names are generated without leading zeros.
+    let number = &name[2..];
+    number.iter().all(u8::is_ascii_digit) && (number[0] != b'0' || number.len() == 1)
+}
+
+/// Turns a missing property into `None`, passing any other error through.
+fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::PropertyNotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Checks that no node in the subtree of `node` is nested deeper than `MAX_FDT_DEPTH`.
+fn check_depth<'a>(node: &FdtNode<'a>) -> Result<(), Error> {
+    if node.depth() > MAX_FDT_DEPTH {
+        return Err(Error::CorruptedFdt);
+    }
+
+    let mut child = node.first_child();
+    while let Some(node) = child {
+        check_depth(&node)?;
+        child = node.next_sibling();
+    }
+
+    Ok(())
+}
+
 impl<'a> FdtNode<'a> {
-    /// TODO(HfO2): This function is marked `inline(never)`, to prevent stack overflow. It is still
-    /// mysterious why inlining this function into ManifestVm::new makes stack overflow.
-    #[inline(never)]
-    fn read_string(&self, property: *const u8, out: &mut [u8]) -> Result<(), Error> {
+    /// Reads the value of a property, rejecting values longer than `MAX_PROPERTY_LEN`.
+    fn read_property_checked(&self, property: *const u8) -> Result<&'a [u8], Error> {
         let data = self
             .read_property(property)
             .map_err(|_| Error::PropertyNotFound)?;
 
+        if data.len() > MAX_PROPERTY_LEN {
+            return Err(Error::PropertyTooLarge);
+        }
+
+        Ok(data)
+    }
+
+    /// TODO(HfO2): This function is marked `inline(never)`, to prevent stack overflow. It is still
+    /// mysterious why inlining this function into ManifestVm::init makes stack overflow.
+    #[inline(never)]
+    fn read_string(&self, property: *const u8, out: &mut [u8]) -> Result<(), Error> {
+        let data = self.read_property_checked(property)?;
+
         // Require that the value contains exactly one NULL character and that it is the last byte.
         if data.iter().position(|&c| c == b'\0') != Some(data.len() - 1) {
             return Err(Error::MalformedString);
@@ -134,11 +914,17 @@ impl<'a> FdtNode<'a> {
         Ok(())
     }
 
+    /// Reads a property holding exactly as many cells as `A` has elements, e.g., `[u32; 4]`.
+    fn read_property_array<A: Default + AsMut<[u32]>>(
+        &self,
+        property: *const u8,
+    ) -> Result<A, Error> {
+        decode_cells(self.read_property_checked(property)?)
+    }
+
     #[inline(never)]
     fn read_u64(&self, property: *const u8) -> Result<u64, Error> {
-        let data = self
-            .read_property(property)
-            .map_err(|_| Error::PropertyNotFound)?;
+        let data = self.read_property_checked(property)?;
 
         fdt_parse_number(data).ok_or(Error::MalformedInteger)
     }
@@ -149,6 +935,41 @@ impl<'a> FdtNode<'a> {
 
         value.try_into().map_err(|_| Error::IntegerOverflow)
     }
+
+    #[inline(never)]
+    fn read_u32(&self, property: *const u8) -> Result<u32, Error> {
+        let value = self.read_u64(property)?;
+
+        value.try_into().map_err(|_| Error::IntegerOverflow)
+    }
+
+    /// Reads the optional `exception-level` property.
+    fn read_exception_level(&self) -> Result<Option<ExceptionLevel>, Error> {
+        match optional(self.read_u32("exception-level\0".as_ptr()))? {
+            None => Ok(None),
+            Some(0) => Ok(Some(ExceptionLevel::El0)),
+            Some(1) => Ok(Some(ExceptionLevel::El1)),
+            Some(_) => Err(Error::MalformedInteger),
+        }
+    }
+}
+
+/// Decodes big-endian cells into an array, requiring `data` to hold exactly one cell per element.
+fn decode_cells<A: Default + AsMut<[u32]>>(data: &[u8]) -> Result<A, Error> {
+    let mut cells = A::default();
+    if data.len() != cells.as_mut().len() * mem::size_of::<u32>() {
+        return Err(Error::MalformedInteger);
+    }
+
+    for (cell, bytes) in cells
+        .as_mut()
+        .iter_mut()
+        .zip(data.chunks(mem::size_of::<u32>()))
+    {
+        *cell = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+
+    Ok(cells)
 }
 
 /// Represents the value of property whose type is a list of strings. These are encoded as one
@@ -160,9 +981,12 @@ struct StringList {
 
 impl StringList {
     fn read_from<'a>(node: &FdtNode<'a>, property: *const u8) -> Result<Self, Error> {
-        let data = node
-            .read_property(property)
-            .map_err(|_| Error::PropertyNotFound)?;
+        let data = node.read_property_checked(property)?;
+
+        // Even a list of a single empty string has its NULL terminator.
+        if data.is_empty() {
+            return Err(Error::MalformedString);
+        }
 
         // Require that the value ends with a NULL terminator. Other NULL characters separate the
         // string list entries.
@@ -201,12 +1025,12 @@ impl StringList {
         }
     }
 
-    fn contains(&self, asciz: &[u8]) -> bool {
+    fn contains(&self, s: &str) -> bool {
         let mut it = self.clone();
 
         while it.has_next() {
             let entry = it.get_next();
-            if unsafe { entry.iseq(asciz.as_ptr()) } {
+            if entry.matches(s) {
                 return true;
             }
         }
@@ -216,104 +1040,1512 @@ impl StringList {
 }
 
 impl ManifestVm {
-    fn new<'a>(node: &FdtNode<'a>, vm_id: spci_vm_id_t) -> Result<Self, Error> {
-        let mut debug_name: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
-        node.read_string("debug_name\0".as_ptr(), &mut debug_name)?;
-
-        let mut kernel_filename: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
-
-        let (mem_size, vcpu_count) = if vm_id != HF_PRIMARY_VM_ID {
-            node.read_string("kernel_filename\0".as_ptr(), &mut kernel_filename)?;
-            (
-                node.read_u64("mem_size\0".as_ptr())?,
-                node.read_u16("vcpu_count\0".as_ptr())?,
-            )
-        } else {
-            (0, 0)
-        };
+    /// Parses the VM with ID `vm_id` from `node` into `self`, which must be a default VM.
+    fn init<'a>(
+        &mut self,
+        fdt: &FdtNode<'a>,
+        parent: &FdtNode<'a>,
+        node: &FdtNode<'a>,
+        vm_id: spci_vm_id_t,
+        options: &InitOptions,
+    ) -> Result<(), Error> {
+        // Reserved properties are never read as manifest data, so at most they are rejected.
+        if options.strict
+            && RESERVED_PROPERTIES
+                .iter()
+                .any(|name| node.read_property(name.as_ptr()).is_ok())
+        {
+            return Err(Error::ReservedProperty);
+        }
 
-        Ok(Self {
-            debug_name,
-            kernel_filename,
-            mem_size,
-            vcpu_count,
-        })
-    }
-}
+        // The ID is derived from the node name, but may also be stated explicitly.
+        if let Some(id) = optional(node.read_u16("id\0".as_ptr()))? {
+            if id != vm_id {
+                return Err(Error::VmIdMismatch);
+            }
+        }
+        self.vm_id = vm_id;
 
-impl Manifest {
-    /// Parse manifest from FDT.
-    pub fn init<'a>(&mut self, fdt: &FdtNode<'a>) -> Result<(), Error> {
-        let mut vm_name_buf = Default::default();
-        let mut found_primary_vm = false;
-        unsafe {
-            self.vms.set_len(0);
+        match node.read_string("debug_name\0".as_ptr(), &mut self.debug_name) {
+            Err(Error::PropertyNotFound) if options.default_debug_name => {
+                let mut vm_name_buf = Default::default();
+                let vm_name = generate_vm_node_name(&mut vm_name_buf, vm_id);
+                self.debug_name[..vm_name.len()].copy_from_slice(vm_name);
+            }
+            result => result?,
         }
 
-        // Find hypervisor node.
-        let mut hyp_node = fdt.clone();
-        hyp_node
-            .find_child("hypervisor\0".as_ptr())
-            .ok_or(Error::NoHypervisorFdtNode)?;
+        // Interrupt specifiers are pairs of ID and attributes unless the controller says otherwise.
+        let mut interrupt_cells: usize = 2;
+        if let Some(phandle) = optional(node.read_u32("interrupt-parent\0".as_ptr()))? {
+            let (name, parent) = fdt.find_phandle(phandle).ok_or(Error::UnresolvedPhandle)?;
+            if name.len() > self.interrupt_controller.len() {
+                return Err(Error::StringTooLong);
+            }
+            self.interrupt_controller[..name.len()].copy_from_slice(name);
 
-        // Check "compatible" property.
-        let compatible_list = StringList::read_from(&hyp_node, "compatible\0".as_ptr())?;
-        if !compatible_list.contains(b"hafnium,hafnium\0") {
-            return Err(Error::NotCompatible);
+            if let Some(cells) = optional(parent.read_u32("#interrupt-cells\0".as_ptr()))? {
+                interrupt_cells = cells as usize;
+            }
         }
 
-        // Iterate over reserved VM IDs and check no such nodes exist.
-        for vm_id in 0..HF_VM_ID_OFFSET {
-            let mut vm_node = hyp_node.clone();
-            let vm_name = generate_vm_node_name(&mut vm_name_buf, vm_id);
+        read_interrupts(node, interrupt_cells, &mut self.interrupts)?;
 
-            if vm_node.find_child(vm_name.as_ptr()).is_some() {
-                return Err(Error::ReservedVmId);
+        // Each entry of `interrupts-extended` is the phandle of a controller followed by a
+        // specifier of as many cells as that controller says. A VM only has one interrupt
+        // controller, so every entry must refer to the same one.
+        if let Some(mut data) =
+            optional(node.read_property_checked("interrupts-extended\0".as_ptr()))?
+        {
+            if node.read_property("interrupts\0".as_ptr()).is_ok() {
+                return Err(Error::ConflictingProperties);
             }
-        }
 
-        // Iterate over VM nodes until we find one that does not exist.
-        for i in 0..=MAX_VMS as spci_vm_id_t {
-            let vm_id = HF_VM_ID_OFFSET + i;
-            let mut vm_node = hyp_node.clone();
-            let vm_name = generate_vm_node_name(&mut vm_name_buf, vm_id);
+            let mut controller: Option<&[u8]> = None;
+            while !data.is_empty() {
+                if data.len() < mem::size_of::<u32>() {
+                    return Err(Error::MalformedInteger);
+                }
+                let (phandle, rest) = data.split_at(mem::size_of::<u32>());
+                let phandle = u32::from_be_bytes(phandle.try_into().unwrap());
+                let (name, parent) = fdt.find_phandle(phandle).ok_or(Error::UnresolvedPhandle)?;
+                if controller.map_or(false, |controller| controller != name) {
+                    return Err(Error::ConflictingProperties);
+                }
+                controller = Some(name);
 
-            if vm_node.find_child(vm_name.as_ptr()).is_none() {
-                break;
+                let cells = optional(parent.read_u32("#interrupt-cells\0".as_ptr()))?.unwrap_or(2);
+                let specifier_size = cells as usize * mem::size_of::<u32>();
+                if specifier_size == 0 || rest.len() < specifier_size {
+                    return Err(Error::MalformedInteger);
+                }
+                let (specifier, rest) = rest.split_at(specifier_size);
+                self.interrupts
+                    .try_push(Interrupt::decode(specifier)?)
+                    .map_err(|_| Error::TooManyInterrupts)?;
+                data = rest;
             }
 
-            if i == MAX_VMS as spci_vm_id_t {
-                return Err(Error::TooManyVms);
+            if let Some(name) = controller {
+                if name.len() > self.interrupt_controller.len() {
+                    return Err(Error::StringTooLong);
+                }
+                self.interrupt_controller = Default::default();
+                self.interrupt_controller[..name.len()].copy_from_slice(name);
             }
+        }
+
+        optional(node.read_string("ramdisk_filename\0".as_ptr(), &mut self.ramdisk_filename))?;
+
+        // Kernels interpret their boot parameters themselves, so any bytes are accepted.
+        self.kernel_boot_params =
+            match optional(node.read_property_checked("kernel-boot-params\0".as_ptr()))? {
+                None => None,
+                Some(data) if data.len() > MAX_KERNEL_BOOT_PARAMS_LENGTH => {
+                    return Err(Error::PropertyTooLarge)
+                }
+                Some(data) => Some(data.iter().cloned().collect()),
+            };
 
-            if vm_id == HF_PRIMARY_VM_ID {
-                assert!(found_primary_vm == false); // sanity check
-                found_primary_vm = true;
+        if let Some(data) = optional(node.read_property_checked("uuid\0".as_ptr()))? {
+            let uuid_size = mem::size_of::<[u32; 4]>();
+            if data.len() % uuid_size != 0 {
+                return Err(Error::MalformedInteger);
             }
 
-            self.vms.push(ManifestVm::new(&vm_node, vm_id)?);
+            for chunk in data.chunks(uuid_size) {
+                self.uuids
+                    .try_push(decode_cells(chunk)?)
+                    .map_err(|_| Error::TooManyUuids)?;
+            }
         }
 
-        if !found_primary_vm {
-            Err(Error::NoPrimaryVm)
-        } else {
-            Ok(())
-        }
-    }
-}
+        self.secure = vm_id >= SECURE_VM_ID_BASE || node.read_property("secure\0".as_ptr()).is_ok();
+        self.exception_level = node.read_exception_level()?;
 
-#[cfg(test)]
-mod test {
-    extern crate std;
-    use std::fmt::Write as _;
-    use std::io::Write;
-    use std::mem::MaybeUninit;
-    use std::process::*;
-    use std::string::String;
-    use std::vec::Vec;
+        Self::read_execution_contexts(node, self.secure, &mut self.execution_contexts)?;
+        self.execution_ctx_count = optional(node.read_u16("execution-ctx-count\0".as_ptr()))?;
 
-    use super::*;
-    use crate::utils::*;
+        self.boot_info = node.read_property("boot-info\0".as_ptr()).is_ok();
+        self.gp_register = optional(node.read_u32("gp-register-num\0".as_ptr()))?;
+        self.notification_support = node
+            .read_property("notification-support\0".as_ptr())
+            .is_ok();
+        self.notification_count = optional(node.read_u32("notification-count\0".as_ptr()))?;
+        self.time_slice_ms = optional(node.read_u32("time-slice-ms\0".as_ptr()))?;
+
+        // Affinity is given either as a list of CPU IDs or as the equivalent bitmask.
+        let cpus = optional(node.read_property_checked("cpus\0".as_ptr()))?;
+        let affinity_mask = optional(node.read_u64("affinity-mask\0".as_ptr()))?;
+        self.cpu_affinity = match (cpus, affinity_mask) {
+            (Some(_), Some(_)) => return Err(Error::ConflictingProperties),
+            (Some(data), None) => {
+                if data.len() % mem::size_of::<u32>() != 0 {
+                    return Err(Error::MalformedInteger);
+                }
+
+                let mut mask = 0u64;
+                for cell in data.chunks(mem::size_of::<u32>()) {
+                    let cpu = u32::from_be_bytes(cell.try_into().unwrap()) as usize;
+                    if cpu >= MAX_CPUS {
+                        return Err(Error::InvalidCpuId(vm_id));
+                    }
+                    mask |= 1 << cpu;
+                }
+                Some(mask)
+            }
+            (None, mask) => mask,
+        };
+        self.psci_suspend_mask = optional(node.read_u64("psci-suspend-mask\0".as_ptr()))?;
+        self.log_level = match optional(node.read_u32("log-level\0".as_ptr()))? {
+            Some(level) => level.try_into().map_err(|_| Error::MalformedInteger)?,
+            None => DEFAULT_LOG_LEVEL,
+        };
+        self.load_flags = optional(node.read_u32("load-flags\0".as_ptr()))?.unwrap_or(0);
+
+        // Follows the standard DT `status` values, where only "okay" and "disabled" are meaningful.
+        // Neither is too long for the buffer, so an overlong status is just malformed.
+        let mut status: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
+        self.enabled = match node.read_string("status\0".as_ptr(), &mut status) {
+            Ok(()) => match as_asciz(&status) {
+                b"okay" => true,
+                b"disabled" => false,
+                _ => return Err(Error::MalformedString),
+            },
+            Err(Error::PropertyNotFound) => true,
+            Err(Error::StringTooLong) => return Err(Error::MalformedString),
+            Err(e) => return Err(e),
+        };
+
+        let mut policy: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
+        self.restart_policy =
+            match optional(node.read_string("restart-policy\0".as_ptr(), &mut policy))? {
+                None => RestartPolicy::Never,
+                Some(()) => match as_asciz(&policy) {
+                    b"never" => RestartPolicy::Never,
+                    b"on-failure" => RestartPolicy::OnFailure,
+                    b"always" => RestartPolicy::Always,
+                    _ => return Err(Error::MalformedString),
+                },
+            };
+
+        if vm_id != HF_PRIMARY_VM_ID {
+            node.read_string("kernel_filename\0".as_ptr(), &mut self.kernel_filename)?;
+
+            // The VM's memory may instead be given as a standard `reg = <base size>` pair, sized
+            // by the cell counts of the hypervisor node, but not both ways at once.
+            if let Some(reg) = optional(node.read_property_checked("reg\0".as_ptr()))? {
+                if node.read_property("mem_size\0".as_ptr()).is_ok()
+                    || node.read_property("boot_address\0".as_ptr()).is_ok()
+                {
+                    return Err(Error::ConflictingProperties);
+                }
+
+                let (address_cells, size_cells) = read_cell_counts(parent)?;
+                let address_size = address_cells * mem::size_of::<u32>();
+                if reg.len() != address_size + size_cells * mem::size_of::<u32>() {
+                    return Err(Error::MalformedInteger);
+                }
+                let (base, size) = reg.split_at(address_size);
+                self.boot_address = Some(fdt_parse_number(base).ok_or(Error::MalformedInteger)?);
+                self.mem_size = fdt_parse_number(size).ok_or(Error::MalformedInteger)?;
+            } else {
+                self.mem_size = node.read_u64("mem_size\0".as_ptr())?;
+                self.boot_address = optional(node.read_u64("boot_address\0".as_ptr()))?;
+            }
+            if options.strict
+                && (self.mem_size == u64::max_value()
+                    || self.boot_address == Some(u64::max_value()))
+            {
+                return Err(Error::MalformedInteger);
+            }
+
+            self.kernel_size = optional(node.read_u64("kernel-size\0".as_ptr()))?;
+            self.vcpu_count = node.read_u16("vcpu_count\0".as_ptr())?;
+            self.watchdog_timeout_ms = optional(node.read_u32("watchdog-timeout-ms\0".as_ptr()))?;
+            self.boot_order = optional(node.read_u16("boot-order\0".as_ptr()))?;
+        } else {
+            // The primary VM is loaded by the bootloader, not from a file named in the manifest.
+            if node.read_property("kernel_filename\0".as_ptr()).is_ok() {
+                return Err(Error::UnexpectedProperty);
+            }
+        }
+
+        // Regions may be relative to the boot address, so read them once it is known.
+        Self::read_memory_regions(
+            node,
+            vm_id,
+            self.boot_address,
+            interrupt_cells,
+            &mut self.memory_regions,
+        )?;
+
+        self.entry_offset = optional(node.read_u64("entry-offset\0".as_ptr()))?.unwrap_or(0);
+
+        self.validate()
+    }
+
+    /// Checks the invariants relating the VM's fields to each other, which hold for any VM parsed
+    /// from a manifest.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.secure != self.exception_level.is_some() {
+            return Err(Error::InconsistentSecurityState);
+        }
+
+        if !self.execution_contexts.is_empty() {
+            let total = self
+                .execution_contexts
+                .iter()
+                .map(|context| u32::from(context.vcpu_count))
+                .sum::<u32>();
+            if self.execution_ctx_count.map(u32::from) != Some(total) {
+                return Err(Error::InconsistentExecutionContexts);
+            }
+        }
+
+        // The boot information blob is useless unless the VM knows where to find it.
+        if self.boot_info && self.gp_register.is_none() {
+            return Err(Error::ConflictingProperties);
+        }
+
+        if let Some(count) = self.notification_count {
+            if count > MAX_NOTIFICATIONS {
+                return Err(Error::InvalidNotificationCount);
+            }
+            if !self.notification_support {
+                return Err(Error::ConflictingProperties);
+            }
+        }
+
+        // Zero-length time slices and watchdogs which expire immediately are meaningless.
+        if self.time_slice_ms == Some(0) || self.watchdog_timeout_ms == Some(0) {
+            return Err(Error::MalformedInteger);
+        }
+
+        if self.log_level > MAX_LOG_LEVEL || self.load_flags & !LOAD_FLAGS_MASK != 0 {
+            return Err(Error::MalformedInteger);
+        }
+
+        // A VM allowed to run on no CPU could never be scheduled.
+        if let Some(mask) = self.cpu_affinity {
+            if mask == 0 {
+                return Err(Error::MalformedInteger);
+            }
+            if mask >> MAX_CPUS != 0 {
+                return Err(Error::InvalidCpuId(self.vm_id));
+            }
+        }
+
+        for region in &self.memory_regions {
+            region.validate()?;
+        }
+
+        // The image is loaded into the VM's memory, so it must fit.
+        if self.kernel_size.map_or(false, |size| size > self.mem_size) {
+            return Err(Error::InvalidArgument);
+        }
+
+        if self.vcpu_count as usize > MAX_CPUS {
+            return Err(Error::VcpuCountExceedsCpus(self.vm_id));
+        }
+
+        if let Some(address) = self.boot_address {
+            if address.checked_add(self.mem_size).is_none() {
+                return Err(Error::IntegerOverflow);
+            }
+        }
+
+        // The entry point must lie within the VM's memory, if the manifest says how much it has.
+        if self.mem_size != 0 && self.entry_offset >= self.mem_size {
+            return Err(Error::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    /// Parses the `ctx@N` children of the VM node, in order. Like the VM itself, each context
+    /// declares an exception level if and only if the VM is `secure`.
+    fn read_execution_contexts<'a>(
+        node: &FdtNode<'a>,
+        secure: bool,
+        contexts: &mut ArrayVec<[ExecutionContext; MAX_EXECUTION_CONTEXTS]>,
+    ) -> Result<(), Error> {
+        for name in node.child_names().filter(|name| name.starts_with(b"ctx@")) {
+            let mut ctx_node = node.clone();
+            ctx_node
+                .find_child(name.as_ptr())
+                .ok_or(Error::CorruptedFdt)?;
+
+            let context = ExecutionContext {
+                vcpu_count: ctx_node.read_u16("vcpu_count\0".as_ptr())?,
+                exception_level: ctx_node.read_exception_level()?,
+            };
+            if context.vcpu_count == 0 {
+                return Err(Error::MalformedInteger);
+            }
+            if secure != context.exception_level.is_some() {
+                return Err(Error::InconsistentSecurityState);
+            }
+
+            contexts
+                .try_push(context)
+                .map_err(|_| Error::TooManyExecutionContexts)?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses the children of the VM's `memory-regions` node, if there is one.
+    fn read_memory_regions<'a>(
+        node: &FdtNode<'a>,
+        vm_id: spci_vm_id_t,
+        boot_address: Option<u64>,
+        interrupt_cells: usize,
+        regions: &mut ArrayVec<[MemoryRegion; MAX_MEMORY_REGIONS]>,
+    ) -> Result<(), Error> {
+        let mut region_node = node.clone();
+        if region_node
+            .find_child("memory-regions\0".as_ptr())
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        let mut child = region_node.first_child();
+        while let Some(region_node) = child {
+            let region = MemoryRegion::new(&region_node, boot_address, interrupt_cells)?;
+
+            if regions
+                .iter()
+                .any(|r| region.base_address < r.end() && r.base_address < region.end())
+            {
+                return Err(Error::MemoryRegionOverlap(vm_id, vm_id));
+            }
+
+            regions
+                .try_push(region)
+                .map_err(|_| Error::TooManyMemoryRegions)?;
+
+            child = region_node.next_sibling();
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the VM's images must be signature-checked before booting it.
+    pub fn verify_signature(&self) -> bool {
+        self.load_flags & LOAD_FLAG_VERIFY_SIGNATURE != 0
+    }
+
+    /// Returns whether the VM's images must be decrypted while loading them.
+    pub fn decrypt(&self) -> bool {
+        self.load_flags & LOAD_FLAG_DECRYPT != 0
+    }
+
+    /// Tells the format of the kernel image from the extension of `kernel_filename`, ignoring
+    /// case.
+    pub fn kernel_kind(&self) -> KernelKind {
+        let filename = as_asciz(&self.kernel_filename);
+        let extension = match filename.iter().rposition(|&c| c == b'.') {
+            Some(dot) => &filename[dot + 1..],
+            None => return KernelKind::Raw,
+        };
+
+        if extension.eq_ignore_ascii_case(b"elf") {
+            KernelKind::Elf
+        } else if extension.eq_ignore_ascii_case(b"itb") {
+            KernelKind::Fit
+        } else {
+            KernelKind::Raw
+        }
+    }
+
+    /// Returns the `(id, attributes)` of each of the VM's interrupts, those of the VM followed by
+    /// those of its device regions, once per ID. Fails if an ID is declared more than once with
+    /// different attributes.
+    pub fn all_interrupts<'a>(&'a self) -> Result<impl Iterator<Item = (u32, u32)> + 'a, Error> {
+        let interrupts = move || {
+            self.interrupts.iter().chain(
+                self.memory_regions
+                    .iter()
+                    .flat_map(|region| region.interrupts.iter()),
+            )
+        };
+        for (i, interrupt) in interrupts().enumerate() {
+            if interrupts()
+                .take(i)
+                .any(|other| other.id == interrupt.id && other.attributes != interrupt.attributes)
+            {
+                return Err(Error::DuplicateInterrupt);
+            }
+        }
+
+        Ok(interrupts()
+            .enumerate()
+            .filter(move |(i, interrupt)| {
+                !interrupts().take(*i).any(|other| other.id == interrupt.id)
+            })
+            .map(|(_, interrupt)| (interrupt.id, interrupt.attributes)))
+    }
+
+    /// Returns a snapshot of the VM's ID, vCPU count and memory size.
+    pub fn summary(&self) -> VmSummary {
+        VmSummary {
+            id: self.vm_id,
+            vcpu_count: self.vcpu_count,
+            mem_size: self.mem_size,
+        }
+    }
+
+    /// Returns the `[begin, end)` ranges of physical memory claimed by the VM: its boot region if
+    /// it has a fixed `boot_address`, followed by its memory regions.
+    pub fn memory_ranges<'a>(&'a self) -> impl Iterator<Item = (u64, u64)> + 'a {
+        self.boot_address
+            .map(|address| (address, address + self.mem_size))
+            .into_iter()
+            .chain(
+                self.memory_regions
+                    .iter()
+                    .map(|region| (region.base_address, region.end())),
+            )
+    }
+}
+
+impl Manifest {
+    /// Creates an empty manifest, with no VMs.
+    pub fn new() -> Self {
+        Self {
+            vms: ArrayVec::new(),
+            reserved_memory: ArrayVec::new(),
+            compatible: ArrayVec::new(),
+            cpu_frequency_hz: None,
+            stack_size: None,
+            min_version: None,
+            max_version: None,
+            bootargs: None,
+            aliases: ArrayVec::new(),
+            fdt_checksum: None,
+            stats: Default::default(),
+        }
+    }
+
+    /// Parse manifest from FDT.
+    pub fn init<'a>(&mut self, fdt: &FdtNode<'a>) -> Result<(), Error> {
+        self.init_with_options(fdt, &Default::default(), None)
+    }
+
+    /// Parse manifest from the FDT blob of `len` bytes at `ptr`.
+    pub unsafe fn init_from_raw(&mut self, ptr: *const u8, len: usize) -> Result<(), Error> {
+        if ptr.is_null() || len == 0 {
+            return Err(Error::InvalidArgument);
+        }
+
+        let fdt = MemIter::from_raw(ptr, len);
+        self.init(&fdt_root(&fdt)?)
+    }
+
+    /// Parse manifest from the FDT blob starting `offset` bytes into `buffer`. The offset must be
+    /// 8-byte aligned and within the buffer.
+    pub fn init_at_offset(&mut self, buffer: &MemIter, offset: usize) -> Result<(), Error> {
+        if !is_aligned(offset, mem::size_of::<u64>()) {
+            return Err(Error::InvalidArgument);
+        }
+
+        let mut fdt = buffer.clone();
+        fdt.advance(offset).map_err(|_| Error::InvalidArgument)?;
+        self.init(&fdt_root(&fdt)?)
+    }
+
+    /// Parse manifest from the FDT blob in `fdt`.
+    pub fn init_from_fdt(&mut self, fdt: &MemIter) -> Result<(), Error> {
+        self.init(&fdt_root(fdt)?)
+    }
+
+    /// Checks that the FDT blob in `fdt` is a manifest which `init` accepts, without storing it.
+    /// Returns the number of VMs.
+    pub fn check(fdt: &MemIter) -> Result<usize, Error> {
+        let mut counter = VmCounter::default();
+        parse(&mut counter, &fdt_root(fdt)?, &Default::default(), None)?;
+        Ok(counter.kept.len())
+    }
+
+    /// Parse manifest from the FDT blob in `fdt`, unless it is unchanged since the last call.
+    /// Returns whether the manifest was parsed again.
+    pub fn reinit_if_changed(&mut self, fdt: &MemIter) -> Result<bool, Error> {
+        let mut hasher = Fnv1aHasher::default();
+        fdt.hash(&mut hasher);
+        let checksum = hasher.finish();
+
+        if self.fdt_checksum == Some(checksum) {
+            return Ok(false);
+        }
+
+        self.init(&fdt_root(fdt)?)?;
+        self.fdt_checksum = Some(checksum);
+        Ok(true)
+    }
+
+    /// Parse manifest from FDT, returning statistics about the work done.
+    #[cfg(feature = "stats")]
+    pub fn init_with_stats<'a>(&mut self, fdt: &FdtNode<'a>) -> Result<ParseStats, Error> {
+        self.init_with_options(fdt, &Default::default(), None)?;
+        Ok(self.stats)
+    }
+
+    /// Parse manifest from FDT, generating a "vmN" `debug_name` for VMs which do not declare one.
+    pub fn init_with_defaults<'a>(&mut self, fdt: &FdtNode<'a>) -> Result<(), Error> {
+        self.init_with_options(
+            fdt,
+            &InitOptions {
+                default_debug_name: true,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Parse manifest from FDT, rejecting nodes and properties which would otherwise be ignored.
+    /// An all-ones `mem_size` or `boot_address`, likely a -1 sentinel, is also rejected.
+    pub fn init_strict<'a>(&mut self, fdt: &FdtNode<'a>) -> Result<(), Error> {
+        self.init_with_options(
+            fdt,
+            &InitOptions {
+                strict: true,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Parse manifest from FDT, parsing the nodes of the reserved VM IDs in `allowed` as VMs
+    /// rather than rejecting them. Those VMs follow the others in `vms`.
+    pub fn init_with_reserved_override<'a>(
+        &mut self,
+        fdt: &FdtNode<'a>,
+        allowed: &[spci_vm_id_t],
+    ) -> Result<(), Error> {
+        self.init_with_options(
+            fdt,
+            &InitOptions {
+                allowed_reserved_ids: allowed,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Parse manifest from FDT, then replace the `kernel_filename` of each secondary VM, which is
+    /// a logical name, with the path `resolver` maps it to.
+    pub fn init_with_resolver<'a>(
+        &mut self,
+        fdt: &FdtNode<'a>,
+        resolver: impl Fn(&MemIter) -> Option<MemIter>,
+    ) -> Result<(), Error> {
+        self.init(fdt)?;
+
+        for vm in self
+            .vms
+            .iter_mut()
+            .filter(|vm| vm.vm_id != HF_PRIMARY_VM_ID)
+        {
+            let name = as_asciz(&vm.kernel_filename);
+            let name = unsafe { MemIter::from_raw(name.as_ptr(), name.len()) };
+            let path = resolver(&name).ok_or(Error::UnresolvedKernel)?;
+
+            // Leave room for the NULL terminator.
+            let mut kernel_filename: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
+            if path.len() >= kernel_filename.len() {
+                return Err(Error::StringTooLong);
+            }
+            path.copy_to(&mut kernel_filename)?;
+            vm.kernel_filename = kernel_filename;
+        }
+
+        Ok(())
+    }
+
+    /// Parse manifest from FDT, leaving out the secondary VMs which fail to parse rather than
+    /// failing altogether. Returns the ID of each VM left out and the error it failed with. Errors
+    /// which are not specific to a secondary VM are still returned.
+    pub fn init_skip_malformed<'a>(&mut self, fdt: &FdtNode<'a>) -> Result<SkippedVms, Error> {
+        let mut skipped = ArrayVec::new();
+        self.init_with_options(fdt, &Default::default(), Some(&mut skipped))?;
+        Ok(skipped)
+    }
+
+    fn init_with_options<'a>(
+        &mut self,
+        fdt: &FdtNode<'a>,
+        options: &InitOptions,
+        skipped: Option<&mut SkippedVms>,
+    ) -> Result<(), Error> {
+        unsafe {
+            self.vms.set_len(0);
+            self.reserved_memory.set_len(0);
+            self.compatible.set_len(0);
+            self.aliases.set_len(0);
+            // Overwrite without dropping, as the manifest may be uninitialized.
+            ptr::write(&mut self.bootargs, None);
+        }
+        self.fdt_checksum = None;
+        self.stats = Default::default();
+
+        parse(self, fdt, options, skipped)
+    }
+
+    /// Returns the secondary VM with the most memory, preferring the lowest ID among equals.
+    pub fn largest_vm(&self) -> Option<&ManifestVm> {
+        self.vms
+            .iter()
+            .filter(|vm| vm.vm_id != HF_PRIMARY_VM_ID)
+            .max_by(|a, b| {
+                a.mem_size
+                    .cmp(&b.mem_size)
+                    .then_with(|| b.vm_id.cmp(&a.vm_id))
+            })
+    }
+
+    /// Returns the number of secure partitions running at S-EL0 and at S-EL1, respectively.
+    pub fn count_by_exception_level(&self) -> (usize, usize) {
+        self.vms
+            .iter()
+            .fold((0, 0), |(el0, el1), vm| match vm.exception_level {
+                Some(ExceptionLevel::El0) => (el0 + 1, el1),
+                Some(ExceptionLevel::El1) => (el0, el1 + 1),
+                None => (el0, el1),
+            })
+    }
+
+    /// Returns the total number of vCPUs of the VMs which may run on physical CPU `cpu`, counting
+    /// VMs without an affinity towards every CPU. The primary VM declares no vCPU count, so it does
+    /// not contribute.
+    pub fn total_vcpus_on_cpu(&self, cpu: u32) -> u32 {
+        self.vms
+            .iter()
+            .filter(|vm| {
+                vm.cpu_affinity.map_or(true, |mask| {
+                    mask.checked_shr(cpu).map_or(false, |m| m & 1 != 0)
+                })
+            })
+            .map(|vm| u32::from(vm.vcpu_count))
+            .sum()
+    }
+
+    /// Returns the VM which declares `uuid` among its UUIDs, if any.
+    pub fn find_vm_by_uuid(&self, uuid: &[u32; 4]) -> Option<&ManifestVm> {
+        self.vms.iter().find(|vm| vm.uuids.contains(uuid))
+    }
+
+    /// Returns the VM whose debug name is `name`, or failing that the VM `name` is an alias of.
+    pub fn find_vm_by_name(&self, name: &str) -> Option<&ManifestVm> {
+        let name = name.as_bytes();
+        self.vms
+            .iter()
+            .find(|vm| as_asciz(&vm.debug_name) == name)
+            .or_else(|| {
+                let (_, vm_id) = self
+                    .aliases
+                    .iter()
+                    .find(|(alias, _)| as_asciz(alias) == name)?;
+                self.get_vm(*vm_id)
+            })
+    }
+
+    /// Returns whether the manifest supports Hafnium version `version`. An unspecified bound
+    /// accepts any version on its side.
+    pub fn is_compatible(&self, version: u32) -> bool {
+        self.min_version.map_or(true, |min| min <= version)
+            && self.max_version.map_or(true, |max| version <= max)
+    }
+
+    /// Returns the VM with ID `vm_id`, if any.
+    pub fn get_vm(&self, vm_id: spci_vm_id_t) -> Option<&ManifestVm> {
+        self.vms.iter().find(|vm| vm.vm_id == vm_id)
+    }
+
+    /// Returns the primary VM, which is always parsed first.
+    pub fn primary(&self) -> Result<&ManifestVm, Error> {
+        self.vms
+            .first()
+            .filter(|vm| vm.vm_id == HF_PRIMARY_VM_ID)
+            .ok_or(Error::NoPrimaryVm)
+    }
+
+    /// Returns the ID and kernel filename, without the NULL terminator, of each secondary VM.
+    pub fn iter_kernels(&self) -> impl Iterator<Item = (spci_vm_id_t, &[u8])> {
+        self.vms
+            .iter()
+            .filter(|vm| vm.vm_id != HF_PRIMARY_VM_ID)
+            .map(|vm| (vm.vm_id, as_asciz(&vm.kernel_filename)))
+    }
+
+    /// Writes one line per VM with its ID, debug name, vCPU count and memory size.
+    pub fn write_summary(&self, w: &mut impl Write) -> fmt::Result {
+        for vm in self.vms.iter() {
+            let name = core::str::from_utf8(as_asciz(&vm.debug_name)).map_err(|_| fmt::Error)?;
+            writeln!(
+                w,
+                "VM {}: {}, {} vCPUs, {:#x} bytes",
+                vm.vm_id, name, vm.vcpu_count, vm.mem_size
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that no two VMs claim overlapping ranges of physical memory.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (i, vm) in self.vms.iter().enumerate() {
+            for other in &self.vms[i + 1..] {
+                for (begin, end) in vm.memory_ranges() {
+                    if other
+                        .memory_ranges()
+                        .any(|(other_begin, other_end)| begin < other_end && other_begin < end)
+                    {
+                        return Err(Error::MemoryRegionOverlap(vm.vm_id, other.vm_id));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `(begin, end, owner)` ranges of physical memory claimed by the VMs and by
+    /// reserved memory, sorted by address. Reserved memory is owned by `RESERVED_MEMORY_OWNER`.
+    /// Overlaps are not checked here; see `validate`.
+    pub fn memory_map(&self) -> ArrayVec<[(u64, u64, spci_vm_id_t); MAX_MEMORY_MAP_ENTRIES]> {
+        let mut map: ArrayVec<[_; MAX_MEMORY_MAP_ENTRIES]> = self
+            .vms
+            .iter()
+            .flat_map(|vm| {
+                vm.memory_ranges()
+                    .map(move |(begin, end)| (begin, end, vm.vm_id))
+            })
+            .chain(
+                self.reserved_memory
+                    .iter()
+                    .map(|r| (r.base, r.base + r.size, RESERVED_MEMORY_OWNER)),
+            )
+            .collect();
+        map.sort_unstable();
+        map
+    }
+
+    /// Returns the end of the highest range in `memory_map`, i.e., where memory no VM or reserved
+    /// region claims begins. Returns 0 if there are no such ranges.
+    pub fn memory_high_watermark(&self) -> u64 {
+        self.memory_map()
+            .iter()
+            .map(|&(_, end, _)| end)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Checks that no two secondary VMs share a boot order. Any number of VMs may leave it
+    /// unspecified, as `boot_sequence` then orders them by ID.
+    pub fn validate_boot_order_unique(&self) -> Result<(), Error> {
+        let secondaries = || {
+            self.vms
+                .iter()
+                .filter(|vm| vm.vm_id != HF_PRIMARY_VM_ID && vm.boot_order.is_some())
+        };
+
+        for (i, vm) in secondaries().enumerate() {
+            if let Some(other) = secondaries()
+                .skip(i + 1)
+                .find(|other| other.boot_order == vm.boot_order)
+            {
+                return Err(Error::DuplicateBootOrder(vm.vm_id, other.vm_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs every check on the manifest against the limits of `platform`, returning the first
+    /// failure. Duplicate debug names are already rejected when parsing.
+    pub fn validate_all(&self, platform: &PlatformLimits) -> Result<(), Error> {
+        for vm in &self.vms {
+            if vm.vcpu_count as usize > platform.cpu_count {
+                return Err(Error::VcpuCountExceedsCpus(vm.vm_id));
+            }
+
+            // Parsing only checks against `MAX_CPUS`, which the platform may have fewer of.
+            let beyond_platform = vm.cpu_affinity.map_or(false, |mask| {
+                mask.checked_shr(platform.cpu_count as u32)
+                    .map_or(false, |m| m != 0)
+            });
+            if beyond_platform {
+                return Err(Error::InvalidCpuId(vm.vm_id));
+            }
+        }
+
+        for cpu in 0..platform.cpu_count as u32 {
+            if self.total_vcpus_on_cpu(cpu) > platform.max_vcpus_per_cpu {
+                return Err(Error::CpuOversubscribed(cpu));
+            }
+        }
+
+        let total_mem_size = self
+            .vms
+            .iter()
+            .try_fold(0u64, |total, vm| total.checked_add(vm.mem_size))
+            .ok_or(Error::IntegerOverflow)?;
+        if total_mem_size > platform.total_ram {
+            return Err(Error::InsufficientMemory);
+        }
+
+        self.validate()?;
+        self.validate_unique_uuids()?;
+        self.validate_boot_order_unique()
+    }
+
+    /// Returns the IDs of the secondary VMs in the order they boot: first those with a boot order,
+    /// in ascending boot order from 0, then the others in ascending ID order.
+    pub fn boot_sequence(&self) -> ArrayVec<[spci_vm_id_t; MAX_VMS]> {
+        let mut secondaries: ArrayVec<[&ManifestVm; MAX_VMS]> = self
+            .vms
+            .iter()
+            .filter(|vm| vm.vm_id != HF_PRIMARY_VM_ID)
+            .collect();
+        secondaries.sort_unstable_by_key(|vm| (vm.boot_order.is_none(), vm.boot_order, vm.vm_id));
+
+        secondaries.iter().map(|vm| vm.vm_id).collect()
+    }
+
+    /// Checks that no UUID is declared by more than one VM. The all-zero UUID stands for no UUID,
+    /// such as that of the primary VM, so it may be shared.
+    pub fn validate_unique_uuids(&self) -> Result<(), Error> {
+        for (i, vm) in self.vms.iter().enumerate() {
+            for uuid in vm.uuids.iter().filter(|uuid| **uuid != [0; 4]) {
+                if let Some(other) = self.vms[i + 1..]
+                    .iter()
+                    .find(|other| other.uuids.contains(uuid))
+                {
+                    return Err(Error::DuplicateUuid(vm.vm_id, other.vm_id));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the manifest in `fdt` into `sink`. If `skipped` is given, the secondary VMs which fail to
+/// parse are left out and listed there rather than failing the parse.
+fn parse<'a, S: ManifestSink>(
+    sink: &mut S,
+    fdt: &FdtNode<'a>,
+    options: &InitOptions,
+    mut skipped: Option<&mut SkippedVms>,
+) -> Result<(), Error> {
+    let mut vm_name_buf = Default::default();
+    let mut found_primary_vm = false;
+
+    // Reject trees whose end-node tokens do not match their begin-node tokens.
+    fdt.check_balanced().ok_or(Error::CorruptedFdt)?;
+
+    // Find hypervisor node.
+    let mut hyp_node = fdt.clone();
+    sink.stats().visit_node();
+    hyp_node
+        .find_child("hypervisor\0".as_ptr())
+        .ok_or(Error::NoHypervisorFdtNode)?;
+    sink.stats().read(&hyp_node);
+
+    // Check "compatible" property.
+    let mut compatible_list = StringList::read_from(&hyp_node, "compatible\0".as_ptr())?;
+    if !compatible_list.contains("hafnium,hafnium") {
+        return Err(Error::NotCompatible);
+    }
+
+    while compatible_list.has_next() {
+        let entry = compatible_list.get_next();
+
+        // Leave room for the NULL terminator.
+        let mut compatible: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
+        if entry.len() >= compatible.len() {
+            return Err(Error::StringTooLong);
+        }
+        entry.copy_to(&mut compatible)?;
+
+        sink.compatible()
+            .try_push(compatible)
+            .map_err(|_| Error::TooManyCompatibleStrings)?;
+    }
+
+    let cpu_frequency_hz = optional(hyp_node.read_u64("cpu-frequency\0".as_ptr()))?;
+    if cpu_frequency_hz == Some(0) {
+        return Err(Error::MalformedInteger);
+    }
+
+    let stack_size = optional(hyp_node.read_u64("stack-size\0".as_ptr()))?;
+    if let Some(stack_size) = stack_size {
+        if stack_size == 0 {
+            return Err(Error::MalformedInteger);
+        }
+        if !is_aligned(stack_size as usize, PAGE_SIZE) {
+            return Err(Error::MisalignedAddress);
+        }
+    }
+
+    let min_version = optional(hyp_node.read_u32("hafnium,min-version\0".as_ptr()))?;
+    let max_version = optional(hyp_node.read_u32("hafnium,max-version\0".as_ptr()))?;
+    if let (Some(min), Some(max)) = (min_version, max_version) {
+        if max < min {
+            return Err(Error::MalformedInteger);
+        }
+    }
+
+    sink.set_hypervisor_properties(cpu_frequency_hz, stack_size, min_version, max_version);
+
+    // Catch manifests built for another platform.
+    if let Some(max_cpus) = optional(hyp_node.read_u32("max-cpus\0".as_ptr()))? {
+        if max_cpus as usize != MAX_CPUS {
+            return Err(Error::CpuCountMismatch);
+        }
+    }
+
+    // Reject pathologically nested nodes before walking them.
+    check_depth(&hyp_node)?;
+
+    // Catch misspelled VM nodes, which would otherwise be silently skipped.
+    if options.strict && !hyp_node.child_names().all(is_vm_node_name) {
+        return Err(Error::UnknownHypervisorChild);
+    }
+
+    // Iterate over reserved VM IDs and check no such nodes exist.
+    for vm_id in 0..HF_VM_ID_OFFSET {
+        let mut vm_node = hyp_node.clone();
+        let vm_name = generate_vm_node_name(&mut vm_name_buf, vm_id);
+
+        sink.stats().visit_node();
+        if vm_node.find_child(vm_name.as_ptr()).is_some()
+            && !options.allowed_reserved_ids.contains(&vm_id)
+        {
+            return Err(Error::ReservedVmId(vm_id));
+        }
+    }
+
+    // Iterate over VM nodes until we find one that does not exist.
+    for i in 0..=MAX_VMS as spci_vm_id_t {
+        let vm_id = HF_VM_ID_OFFSET + i;
+        let mut vm_node = hyp_node.clone();
+        let vm_name = generate_vm_node_name(&mut vm_name_buf, vm_id);
+
+        sink.stats().visit_node();
+        if vm_node.find_child(vm_name.as_ptr()).is_none() {
+            break;
+        }
+
+        if i == MAX_VMS as spci_vm_id_t {
+            return Err(Error::TooManyVms);
+        }
+
+        if vm_id == HF_PRIMARY_VM_ID {
+            assert!(found_primary_vm == false); // sanity check
+            found_primary_vm = true;
+        }
+
+        match (
+            parse_vm(sink, fdt, &hyp_node, &vm_node, vm_id, options),
+            &mut skipped,
+        ) {
+            (Err(e), Some(skipped)) if vm_id != HF_PRIMARY_VM_ID => {
+                skipped.push((vm_id, e));
+                continue;
+            }
+            (result, _) => result?,
+        }
+
+        sink.keep_vm()?;
+        sink.stats().vm(&vm_node);
+    }
+
+    if !found_primary_vm {
+        return Err(Error::NoPrimaryVm);
+    }
+
+    // VMs with allowed reserved IDs follow the others, so that the primary VM remains first.
+    for &vm_id in options.allowed_reserved_ids {
+        if vm_id >= HF_VM_ID_OFFSET || sink.has_vm(vm_id) {
+            continue;
+        }
+
+        let mut vm_node = hyp_node.clone();
+        let vm_name = generate_vm_node_name(&mut vm_name_buf, vm_id);
+
+        sink.stats().visit_node();
+        if vm_node.find_child(vm_name.as_ptr()).is_none() {
+            continue;
+        }
+
+        parse_vm(sink, fdt, &hyp_node, &vm_node, vm_id, options)?;
+        sink.keep_vm()?;
+        sink.stats().vm(&vm_node);
+    }
+
+    init_bootargs(sink, fdt)?;
+    init_aliases(sink, fdt)?;
+    init_reserved_memory(sink, fdt)
+}
+
+/// Parses the VM with ID `vm_id` from `node` in place, into the slot `sink` gives. Discards the
+/// slot if that fails.
+fn parse_vm<'a, S: ManifestSink>(
+    sink: &mut S,
+    fdt: &FdtNode<'a>,
+    parent: &FdtNode<'a>,
+    node: &FdtNode<'a>,
+    vm_id: spci_vm_id_t,
+    options: &InitOptions,
+) -> Result<(), Error> {
+    let result = sink.vm_slot()?.init(fdt, parent, node, vm_id, options);
+    if result.is_err() {
+        sink.discard_vm();
+    }
+    result
+}
+
+/// Reads the `bootargs` of the top-level `chosen` node, if there is one.
+fn init_bootargs<'a, S: ManifestSink>(sink: &mut S, fdt: &FdtNode<'a>) -> Result<(), Error> {
+    let mut node = fdt.clone();
+    sink.stats().visit_node();
+    if node.find_child("chosen\0".as_ptr()).is_none() {
+        return Ok(());
+    }
+
+    let mut bootargs = [0; MAX_BOOTARGS_LENGTH];
+    if optional(node.read_string("bootargs\0".as_ptr(), &mut bootargs))?.is_some() {
+        sink.set_bootargs(as_asciz(&bootargs).iter().cloned().collect());
+    }
+
+    Ok(())
+}
+
+/// Reads the top-level `aliases` node, if there is one, keeping the aliases of VM nodes.
+fn init_aliases<'a, S: ManifestSink>(sink: &mut S, fdt: &FdtNode<'a>) -> Result<(), Error> {
+    let mut node = fdt.clone();
+    sink.stats().visit_node();
+    if node.find_child("aliases\0".as_ptr()).is_none() {
+        return Ok(());
+    }
+
+    for (name, path) in node.properties() {
+        if RESERVED_PROPERTIES
+            .iter()
+            .any(|reserved| as_asciz(reserved.as_bytes()) == name)
+        {
+            continue;
+        }
+
+        // Values are non-empty absolute paths, with exactly one NULL character as the last
+        // byte.
+        if path.len() < 2 || path.iter().position(|&c| c == b'\0') != Some(path.len() - 1) {
+            return Err(Error::MalformedString);
+        }
+        let path = as_asciz(path);
+        if path.first() != Some(&b'/') {
+            return Err(Error::UnresolvedAlias);
+        }
+
+        // Walk down from the root one component at a time.
+        let mut target = fdt.clone();
+        let mut components = ArrayVec::<[&[u8]; MAX_FDT_DEPTH]>::new();
+        for component in path[1..].split(|&c| c == b'/') {
+            let mut component_buf = [0; MANIFEST_MAX_STRING_LENGTH];
+            if component.len() >= component_buf.len() {
+                return Err(Error::UnresolvedAlias);
+            }
+            component_buf[..component.len()].copy_from_slice(component);
+            target
+                .find_child(component_buf.as_ptr())
+                .ok_or(Error::UnresolvedAlias)?;
+            components
+                .try_push(component)
+                .map_err(|_| Error::UnresolvedAlias)?;
+        }
+
+        // Only aliases of VM nodes are of interest to the manifest. The node "vmN" is that of the
+        // VM with ID N.
+        let vm_id = match &components[..] {
+            [b"hypervisor", vm_name] if is_vm_node_name(vm_name) => {
+                core::str::from_utf8(&vm_name[2..])
+                    .ok()
+                    .and_then(|number| number.parse().ok())
+                    .filter(|&vm_id| sink.has_vm(vm_id))
+            }
+            _ => None,
+        };
+        if let Some(vm_id) = vm_id {
+            // Leave room for the NULL terminator.
+            let mut alias: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
+            if name.len() >= alias.len() {
+                return Err(Error::StringTooLong);
+            }
+            alias[..name.len()].copy_from_slice(name);
+
+            sink.aliases()
+                .try_push((alias, vm_id))
+                .map_err(|_| Error::TooManyAliases)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the regions of the top-level `reserved-memory` node, if there is one.
+fn init_reserved_memory<'a, S: ManifestSink>(sink: &mut S, fdt: &FdtNode<'a>) -> Result<(), Error> {
+    let mut node = fdt.clone();
+    sink.stats().visit_node();
+    if node.find_child("reserved-memory\0".as_ptr()).is_none() {
+        return Ok(());
+    }
+
+    let (address_cells, size_cells) = read_cell_counts(&node)?;
+    let address_size = address_cells * mem::size_of::<u32>();
+    let entry_size = address_size + size_cells * mem::size_of::<u32>();
+
+    let mut next = node.first_child();
+    while let Some(child) = next {
+        let reg = child.read_property_checked("reg\0".as_ptr())?;
+        if reg.is_empty() || reg.len() % entry_size != 0 {
+            return Err(Error::MalformedInteger);
+        }
+
+        let no_map = child.read_property("no-map\0".as_ptr()).is_ok();
+        let reusable = child.read_property("reusable\0".as_ptr()).is_ok();
+
+        // If present, `reg-names` labels each entry of `reg` in order.
+        let mut reg_names = optional(StringList::read_from(&child, "reg-names\0".as_ptr()))?;
+
+        for entry in reg.chunks(entry_size) {
+            let mut name: [u8; MANIFEST_MAX_STRING_LENGTH] = Default::default();
+            if let Some(reg_names) = reg_names.as_mut() {
+                if !reg_names.has_next() {
+                    return Err(Error::MalformedString);
+                }
+
+                // Leave room for the NULL terminator.
+                let reg_name = reg_names.get_next();
+                if reg_name.len() >= name.len() {
+                    return Err(Error::StringTooLong);
+                }
+                reg_name.copy_to(&mut name)?;
+            }
+
+            let (base, size) = entry.split_at(address_size);
+            let region = ReservedMemory {
+                base: fdt_parse_number(base).ok_or(Error::MalformedInteger)?,
+                size: fdt_parse_number(size).ok_or(Error::MalformedInteger)?,
+                no_map,
+                reusable,
+                name,
+            };
+
+            if region.base.checked_add(region.size).is_none() {
+                return Err(Error::IntegerOverflow);
+            }
+
+            if sink.reserved_memory().iter().any(|r| r.overlaps(&region)) {
+                return Err(Error::MemoryRegionOverlap(
+                    RESERVED_MEMORY_OWNER,
+                    RESERVED_MEMORY_OWNER,
+                ));
+            }
+
+            sink.reserved_memory()
+                .try_push(region)
+                .map_err(|_| Error::TooManyReservedMemoryRegions)?;
+        }
+
+        if reg_names.map_or(false, |reg_names| reg_names.has_next()) {
+            return Err(Error::MalformedString);
+        }
+
+        next = child.next_sibling();
+    }
+
+    Ok(())
+}
+
+/// Checks that `name` is not the debug name of any of `others`. Empty names identify nothing, so
+/// any number of VMs may leave theirs empty.
+fn check_unique_debug_name<'n>(
+    name: &[u8],
+    mut others: impl Iterator<Item = &'n [u8; MANIFEST_MAX_STRING_LENGTH]>,
+) -> Result<(), Error> {
+    let name = as_asciz(name);
+    if !name.is_empty() && others.any(|other| as_asciz(other) == name) {
+        return Err(Error::DuplicateDebugName);
+    }
+
+    Ok(())
+}
+
+impl ManifestSink for Manifest {
+    fn stats(&mut self) -> &mut ParseStats {
+        &mut self.stats
+    }
+
+    fn compatible(&mut self) -> &mut ArrayVec<[[u8; MANIFEST_MAX_STRING_LENGTH]; MAX_COMPATIBLE]> {
+        &mut self.compatible
+    }
+
+    fn set_hypervisor_properties(
+        &mut self,
+        cpu_frequency_hz: Option<u64>,
+        stack_size: Option<u64>,
+        min_version: Option<u32>,
+        max_version: Option<u32>,
+    ) {
+        self.cpu_frequency_hz = cpu_frequency_hz;
+        self.stack_size = stack_size;
+        self.min_version = min_version;
+        self.max_version = max_version;
+    }
+
+    fn vm_slot(&mut self) -> Result<&mut ManifestVm, Error> {
+        self.vms
+            .try_push(ManifestVm::default())
+            .map_err(|_| Error::TooManyVms)?;
+        Ok(self.vms.last_mut().unwrap())
+    }
+
+    fn keep_vm(&mut self) -> Result<(), Error> {
+        let (vm, others) = self.vms.split_last().unwrap();
+        let result =
+            check_unique_debug_name(&vm.debug_name, others.iter().map(|other| &other.debug_name));
+        if result.is_err() {
+            self.vms.pop();
+        }
+        result
+    }
+
+    fn discard_vm(&mut self) {
+        self.vms.pop();
+    }
+
+    fn has_vm(&self, vm_id: spci_vm_id_t) -> bool {
+        self.get_vm(vm_id).is_some()
+    }
+
+    fn set_bootargs(&mut self, bootargs: ArrayVec<[u8; MAX_BOOTARGS_LENGTH]>) {
+        self.bootargs = Some(bootargs);
+    }
+
+    fn aliases(
+        &mut self,
+    ) -> &mut ArrayVec<[([u8; MANIFEST_MAX_STRING_LENGTH], spci_vm_id_t); MAX_ALIASES]> {
+        &mut self.aliases
+    }
+
+    fn reserved_memory(&mut self) -> &mut ArrayVec<[ReservedMemory; MAX_RESERVED_MEMORY]> {
+        &mut self.reserved_memory
+    }
+}
+
+impl ManifestSink for VmCounter {
+    fn stats(&mut self) -> &mut ParseStats {
+        &mut self.stats
+    }
+
+    fn compatible(&mut self) -> &mut ArrayVec<[[u8; MANIFEST_MAX_STRING_LENGTH]; MAX_COMPATIBLE]> {
+        &mut self.compatible
+    }
+
+    fn set_hypervisor_properties(
+        &mut self,
+        _: Option<u64>,
+        _: Option<u64>,
+        _: Option<u32>,
+        _: Option<u32>,
+    ) {
+    }
+
+    fn vm_slot(&mut self) -> Result<&mut ManifestVm, Error> {
+        if self.kept.is_full() {
+            return Err(Error::TooManyVms);
+        }
+
+        self.vm = ManifestVm::default();
+        Ok(&mut self.vm)
+    }
+
+    fn keep_vm(&mut self) -> Result<(), Error> {
+        check_unique_debug_name(&self.vm.debug_name, self.kept.iter().map(|(name, _)| name))?;
+        self.kept.push((self.vm.debug_name, self.vm.vm_id));
+        Ok(())
+    }
+
+    fn discard_vm(&mut self) {}
+
+    fn has_vm(&self, vm_id: spci_vm_id_t) -> bool {
+        self.kept.iter().any(|&(_, id)| id == vm_id)
+    }
+
+    fn set_bootargs(&mut self, _: ArrayVec<[u8; MAX_BOOTARGS_LENGTH]>) {}
+
+    fn aliases(
+        &mut self,
+    ) -> &mut ArrayVec<[([u8; MANIFEST_MAX_STRING_LENGTH], spci_vm_id_t); MAX_ALIASES]> {
+        &mut self.aliases
+    }
+
+    fn reserved_memory(&mut self) -> &mut ArrayVec<[ReservedMemory; MAX_RESERVED_MEMORY]> {
+        &mut self.reserved_memory
+    }
+}
+
+/// Looks up a VM by ID.
+///
+/// # Panics
+///
+/// Panics if there is no VM with that ID. Use `Manifest::get_vm` if it may be absent.
+impl Index<spci_vm_id_t> for Manifest {
+    type Output = ManifestVm;
+
+    fn index(&self, vm_id: spci_vm_id_t) -> &ManifestVm {
+        self.get_vm(vm_id)
+            .unwrap_or_else(|| panic!("no VM with ID {} in the manifest", vm_id))
+    }
+}
+
+/// Helpers for checking manifests on the build host rather than at boot.
+#[cfg(feature = "std")]
+pub mod host {
+    extern crate std;
+    use std::ffi::CStr;
+    use std::fs::{self, File};
+    use std::os::raw::{c_int, c_void};
+    #[cfg(unix)]
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::ptr;
+
+    use super::*;
+
+    /// The C library's `off_t`, as taken by `mmap`. Linux's `mmap` takes a `long` even on 32-bit
+    /// targets, where the 64-bit variant is `mmap64`.
+    #[cfg(target_os = "linux")]
+    #[allow(non_camel_case_types)]
+    type off_t = std::os::raw::c_long;
+    #[cfg(all(unix, not(target_os = "linux")))]
+    #[allow(non_camel_case_types)]
+    type off_t = i64;
+
+    #[cfg(unix)]
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: off_t,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+
+    #[cfg(unix)]
+    const PROT_READ: c_int = 1;
+    #[cfg(unix)]
+    const MAP_PRIVATE: c_int = 2;
+
+    /// A DTB file mapped read-only into memory, so that it need not be copied into a heap buffer.
+    #[cfg(unix)]
+    pub struct MmapDtb {
+        mem_it: MemIter,
+    }
+
+    #[cfg(unix)]
+    impl MmapDtb {
+        /// Maps the DTB file at `path`. A file that cannot be mapped, including an empty one, is
+        /// reported as `Error::InvalidArgument`.
+        pub fn open(path: &Path) -> Result<Self, Error> {
+            let file = File::open(path).map_err(|_| Error::InvalidArgument)?;
+            let len = file.metadata().map_err(|_| Error::InvalidArgument)?.len() as usize;
+            if len == 0 {
+                return Err(Error::InvalidArgument);
+            }
+
+            let addr = unsafe {
+                mmap(
+                    ptr::null_mut(),
+                    len,
+                    PROT_READ,
+                    MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if addr as isize == -1 {
+                return Err(Error::InvalidArgument);
+            }
+
+            // The mapping outlives the file descriptor.
+            Ok(Self {
+                mem_it: unsafe { MemIter::from_raw(addr as *const u8, len) },
+            })
+        }
+
+        /// Returns an iterator over the mapped bytes, valid for as long as the mapping.
+        pub fn mem_iter(&self) -> &MemIter {
+            &self.mem_it
+        }
+    }
+
+    #[cfg(unix)]
+    impl Drop for MmapDtb {
+        fn drop(&mut self) {
+            unsafe {
+                munmap(self.mem_it.get_next() as *mut c_void, self.mem_it.len());
+            }
+        }
+    }
+
+    /// Parses the manifest in the DTB file at `path`. A file that cannot be read is reported as
+    /// `Error::InvalidArgument`.
+    pub fn validate_file(path: &Path) -> Result<Manifest, Error> {
+        let dtb = fs::read(path).map_err(|_| Error::InvalidArgument)?;
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+
+        let mut manifest = Manifest::new();
+        manifest.init(&fdt_root(&fdt)?)?;
+        Ok(manifest)
+    }
+
+    impl ManifestVm {
+        /// Returns the debug name as a C string, including its NULL terminator, e.g., to pass it
+        /// to C logging functions. `CStr` is not available in `core`, hence only on the host.
+        pub fn debug_name_cstr(&self) -> Result<&CStr, Error> {
+            let len = self
+                .debug_name
+                .iter()
+                .position(|&c| c == b'\0')
+                .ok_or(Error::MalformedString)?;
+
+            CStr::from_bytes_with_nul(&self.debug_name[..=len]).map_err(|_| Error::MalformedString)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::fmt::Write as _;
+    use std::format;
+    use std::io::Write;
+    use std::mem::MaybeUninit;
+    use std::process::*;
+    use std::ptr;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use arrayvec::ArrayString;
+
+    use super::*;
 
     /// Class for programatically building a Device Tree.
     ///
@@ -330,224 +2562,3447 @@ mod test {
         dts: String,
     }
 
-    impl ManifestDtBuilder {
-        fn new() -> Self {
-            let mut builder = Self { dts: String::new() };
-            builder.dts.push_str("/dts-v1/;\n");
-            builder.dts.push_str("\n");
+    impl ManifestDtBuilder {
+        fn new() -> Self {
+            let mut builder = Self { dts: String::new() };
+            builder.dts.push_str("/dts-v1/;\n");
+            builder.dts.push_str("\n");
+
+            // Start root node.
+            builder.start_child("/");
+            builder
+        }
+
+        fn build(&mut self) -> Vec<u8> {
+            self.end_child();
+
+            let mut child = Command::new("../build/image/dtc.py")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .args(&["compile"])
+                .spawn()
+                .unwrap();
+
+            child
+                .stdin
+                .as_mut()
+                .unwrap()
+                .write_all(self.dts.as_bytes())
+                .unwrap();
+
+            child.wait_with_output().unwrap().stdout
+        }
+
+        fn start_child(&mut self, name: &str) -> &mut Self {
+            self.dts.push_str(name);
+            self.dts.push_str(" {\n");
+            self
+        }
+
+        fn end_child(&mut self) -> &mut Self {
+            self.dts.push_str("};\n");
+            self
+        }
+
+        fn compatible(&mut self, value: &[&str]) -> &mut Self {
+            self.string_list_property("compatible", value)
+        }
+
+        fn compatible_hafnium(&mut self) -> &mut Self {
+            self.compatible(&["hafnium,hafnium"])
+        }
+
+        fn debug_name(&mut self, value: &str) -> &mut Self {
+            self.string_property("debug_name", value)
+        }
+
+        fn kernel_filename(&mut self, value: &str) -> &mut Self {
+            self.string_property("kernel_filename", value)
+        }
+
+        fn ramdisk_filename(&mut self, value: &str) -> &mut Self {
+            self.string_property("ramdisk_filename", value)
+        }
+
+        fn vcpu_count(&mut self, value: u64) -> &mut Self {
+            self.integer_property("vcpu_count", value)
+        }
+
+        fn mem_size(&mut self, value: u64) -> &mut Self {
+            self.integer_property("mem_size", value)
+        }
+
+        fn boolean_property(&mut self, name: &str) -> &mut Self {
+            write!(self.dts, "{};\n", name).unwrap();
+            self
+        }
+
+        fn integer_list_property(&mut self, name: &str, value: &[u64]) -> &mut Self {
+            write!(self.dts, "{} = <", name).unwrap();
+            for (i, v) in value.iter().enumerate() {
+                write!(self.dts, "{}{}", if i == 0 { "" } else { " " }, v).unwrap();
+            }
+            self.dts.push_str(">;\n");
+            self
+        }
+
+        fn boot_address(&mut self, value: u64) -> &mut Self {
+            self.integer_property("boot_address", value)
+        }
+
+        fn memory_region(&mut self, name: &str, base_address: u64, pages_count: u64) -> &mut Self {
+            self.start_child(name)
+                .integer_property("base-address", base_address)
+                .integer_property("pages-count", pages_count)
+                .integer_property("attributes", 0)
+                .end_child()
+        }
+
+        fn string_property(&mut self, name: &str, value: &str) -> &mut Self {
+            write!(self.dts, "{} = \"{}\";\n", name, value).unwrap();
+            self
+        }
+
+        fn string_list_property(&mut self, name: &str, value: &[&str]) -> &mut Self {
+            write!(self.dts, "{} = \"", name).unwrap();
+            self.dts.push_str(&value.join("\", \""));
+            self.dts.push_str("\";\n");
+            self
+        }
+
+        fn integer_property(&mut self, name: &str, value: u64) -> &mut Self {
+            write!(self.dts, "{} = <{}>;\n", name, value).unwrap();
+            self
+        }
+    }
+
+    fn get_fdt_root<'a>(dtb: &'a [u8]) -> Option<FdtNode<'a>> {
+        let fdt_header = unsafe { &*(dtb.as_ptr() as *const FdtHeader) };
+
+        let mut node = FdtNode::new_root(fdt_header)?;
+        node.find_child("\0".as_ptr());
+        Some(node)
+    }
+
+    // Byte offsets of the FDT header fields patched by tests.
+    const HEADER_TOTALSIZE: usize = 4;
+    const HEADER_OFF_DT_STRUCT: usize = 8;
+    const HEADER_OFF_DT_STRINGS: usize = 12;
+    const HEADER_LAST_COMP_VERSION: usize = 24;
+    const HEADER_SIZE_DT_STRINGS: usize = 32;
+    const HEADER_SIZE_DT_STRUCT: usize = 36;
+
+    /// Reads the big-endian word `offset` bytes into `dtb`, such as a header field or a token.
+    fn header_field(dtb: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(dtb[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Overwrites the big-endian word `offset` bytes into `dtb`.
+    fn set_header_field(dtb: &mut [u8], offset: usize, value: u32) {
+        dtb[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// Returns the offset of the first `FDT_PROP` token of the structure block.
+    fn first_property(dtb: &[u8]) -> usize {
+        (header_field(dtb, HEADER_OFF_DT_STRUCT) as usize..dtb.len())
+            .step_by(4)
+            .find(|&i| header_field(dtb, i) == 3)
+            .unwrap()
+    }
+
+    #[test]
+    fn vm_node_name() {
+        let mut buf = Default::default();
+        assert_eq!(generate_vm_node_name(&mut buf, 1), b"vm1\0");
+
+        let max = spci_vm_id_t::max_value();
+        let name = generate_vm_node_name(&mut buf, max);
+        assert_eq!(name, format!("vm{}\0", max).as_bytes());
+        assert_eq!(name.len(), VM_NAME_BUF_SIZE);
+    }
+
+    #[test]
+    fn no_hypervisor_node() {
+        let dtb = ManifestDtBuilder::new().build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::NoHypervisorFdtNode);
+    }
+
+    #[test]
+    fn no_compatible_property() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::PropertyNotFound);
+    }
+
+    #[test]
+    fn not_compatible() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible(&["foo,bar"])
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::NotCompatible);
+    }
+
+    #[test]
+    fn compatible_one_of_many() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible(&["foo,bar", "hafnium,hafnium"])
+            .start_child("vm1")
+            .debug_name("primary")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+    }
+
+    #[test]
+    fn no_vm_nodes() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::NoPrimaryVm);
+    }
+
+    #[test]
+    fn default_debug_name() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .end_child()
+            .start_child("vm2")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::PropertyNotFound);
+
+        m.init_with_defaults(&fdt_root).unwrap();
+        assert_eq!(m.vms.len(), 2);
+        assert_eq!(as_asciz(&m.vms[0].debug_name), b"vm1");
+        assert_eq!(as_asciz(&m.vms[1].debug_name), b"vm2");
+    }
+
+    #[test]
+    fn long_string() {
+        fn gen_long_string_dtb(valid: bool) -> Vec<u8> {
+            const LAST_VALID: &'static str = "1234567890123456789012345678901";
+            const FIRST_INVALID: &'static str = "12345678901234567890123456789012";
+            assert_eq!(LAST_VALID.len() + 1, MANIFEST_MAX_STRING_LENGTH);
+            assert_eq!(FIRST_INVALID.len() + 1, MANIFEST_MAX_STRING_LENGTH + 1);
+
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name(if valid { LAST_VALID } else { FIRST_INVALID })
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb_last_valid = gen_long_string_dtb(true);
+        let dtb_first_invalid = gen_long_string_dtb(false);
+
+        let fdt_root = get_fdt_root(&dtb_last_valid).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+
+        let fdt_root = get_fdt_root(&dtb_first_invalid).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::StringTooLong);
+    }
+
+    #[test]
+    fn property_too_large() {
+        let long_name: String = (0..=MAX_PROPERTY_LEN).map(|_| 'a').collect();
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name(&long_name)
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::PropertyTooLarge);
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut builder = ManifestDtBuilder::new();
+        builder
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm");
+
+        for _ in 0..MAX_FDT_DEPTH {
+            builder.start_child("nested");
+        }
+        for _ in 0..MAX_FDT_DEPTH {
+            builder.end_child();
+        }
+
+        let dtb = builder.end_child().end_child().build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::CorruptedFdt);
+    }
+
+    #[test]
+    fn unbalanced_end_node() {
+        let mut dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .build();
+
+        // Replace the trailing `FDT_END` token with an extra `FDT_END_NODE`.
+        let end = (header_field(&dtb, HEADER_OFF_DT_STRUCT)
+            + header_field(&dtb, HEADER_SIZE_DT_STRUCT)) as usize;
+        assert_eq!(header_field(&dtb, end - 4), 9);
+        set_header_field(&mut dtb, end - 4, 2);
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::CorruptedFdt);
+    }
+
+    #[test]
+    fn property_name_out_of_bounds() {
+        let mut dtb = gen_valid_dtb();
+
+        // Point the name of the first property past the end of the strings block.
+        let size_dt_strings = header_field(&dtb, HEADER_SIZE_DT_STRINGS);
+        let prop = first_property(&dtb);
+        set_header_field(&mut dtb, prop + 8, size_dt_strings);
+
+        let mut m = Manifest::new();
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+    }
+
+    #[test]
+    fn oversized_lengths() {
+        let dtb = gen_valid_dtb();
+        let mut m = Manifest::new();
+
+        // A property claiming almost 4 GiB of data.
+        let mut corrupted = dtb.clone();
+        set_header_field(&mut corrupted, first_property(&dtb) + 4, 0xffff_fff0);
+        assert_eq!(
+            unsafe { m.init_from_raw(corrupted.as_ptr(), corrupted.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+
+        // Blocks extending past the end of the blob.
+        for &field in [HEADER_SIZE_DT_STRINGS, HEADER_SIZE_DT_STRUCT].iter() {
+            let mut corrupted = dtb.clone();
+            set_header_field(&mut corrupted, field, 0xffff_fff0);
+            assert_eq!(
+                unsafe { m.init_from_raw(corrupted.as_ptr(), corrupted.len()) }.unwrap_err(),
+                Error::CorruptedFdt
+            );
+        }
+    }
+
+    #[test]
+    fn multiple_root_nodes() {
+        let dtb = gen_valid_dtb();
+
+        // Repeat the root node right before the `FDT_END` token, moving the strings block.
+        let off_dt_struct = header_field(&dtb, HEADER_OFF_DT_STRUCT) as usize;
+        let off_dt_strings = header_field(&dtb, HEADER_OFF_DT_STRINGS) as usize;
+        let root_end = off_dt_strings - mem::size_of::<u32>();
+        let root = dtb[off_dt_struct..root_end].to_vec();
+        let mut corrupted = dtb.clone();
+        corrupted.splice(root_end..root_end, root.iter().cloned());
+        let moved = [
+            HEADER_TOTALSIZE,
+            HEADER_OFF_DT_STRINGS,
+            HEADER_SIZE_DT_STRUCT,
+        ];
+        for &field in moved.iter() {
+            let value = header_field(&corrupted, field) + root.len() as u32;
+            set_header_field(&mut corrupted, field, value);
+        }
+
+        let mut m = Manifest::new();
+        unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap();
+        assert_eq!(
+            unsafe { m.init_from_raw(corrupted.as_ptr(), corrupted.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+    }
+
+    #[test]
+    fn misaligned_struct_block() {
+        let mut dtb = gen_valid_dtb();
+
+        // Insert two bytes in front of the structure block, moving the blocks after the header.
+        let off_dt_struct = header_field(&dtb, HEADER_OFF_DT_STRUCT) as usize;
+        dtb.splice(off_dt_struct..off_dt_struct, [0, 0].iter().cloned());
+        let moved = [
+            HEADER_TOTALSIZE,
+            HEADER_OFF_DT_STRUCT,
+            HEADER_OFF_DT_STRINGS,
+        ];
+        for &field in moved.iter() {
+            let value = header_field(&dtb, field) + 2;
+            set_header_field(&mut dtb, field, value);
+        }
+
+        let mut m = Manifest::new();
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+    }
+
+    #[test]
+    fn misaligned_property() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .string_property("model", "ab")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .build();
+        let model_end = dtb.windows(3).position(|w| w == b"ab\0").unwrap() + 3;
+        let mut m = Manifest::new();
+        unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap();
+
+        // Move the `debug_name` property one byte further, after the padding of `model`, so
+        // that its token is no longer aligned.
+        let mut corrupted = dtb.clone();
+        corrupted.insert(model_end + 1, 0);
+        let moved = [
+            HEADER_TOTALSIZE,
+            HEADER_OFF_DT_STRINGS,
+            HEADER_SIZE_DT_STRUCT,
+        ];
+        for &field in moved.iter() {
+            let value = header_field(&corrupted, field) + 1;
+            set_header_field(&mut corrupted, field, value);
+        }
+        assert_eq!(
+            unsafe { m.init_from_raw(corrupted.as_ptr(), corrupted.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+
+        // Garbage in the padding of `model` likewise means that `debug_name` is not where the
+        // reader looks for it.
+        let mut corrupted = dtb;
+        corrupted[model_end] = 0xff;
+        assert_eq!(
+            unsafe { m.init_from_raw(corrupted.as_ptr(), corrupted.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+    }
+
+    #[test]
+    fn property_flush_with_struct_block_end() {
+        let dtb = ManifestDtBuilder::new()
+            .string_property("model", "ab")
+            .build();
+
+        // Cut the structure block right after the property data, so that it is unpadded and
+        // immediately followed by the strings block.
+        let off_dt_struct = header_field(&dtb, HEADER_OFF_DT_STRUCT) as usize;
+        let off_dt_strings = header_field(&dtb, HEADER_OFF_DT_STRINGS) as usize;
+        let data_end = off_dt_struct
+            + dtb[off_dt_struct..off_dt_strings]
+                .windows(3)
+                .position(|w| w == b"ab\0")
+                .unwrap()
+            + 3;
+
+        let mut flush = dtb[..data_end].to_vec();
+        flush.extend_from_slice(&dtb[off_dt_strings..]);
+        let fields = [
+            (HEADER_TOTALSIZE, flush.len()),
+            (HEADER_OFF_DT_STRINGS, data_end),
+            (HEADER_SIZE_DT_STRUCT, data_end - off_dt_struct),
+        ];
+        for &(field, value) in fields.iter() {
+            set_header_field(&mut flush, field, value as u32);
+        }
+
+        let fdt_root = get_fdt_root(&flush).unwrap();
+        assert_eq!(fdt_root.read_property("model\0".as_ptr()).unwrap(), b"ab\0");
+    }
+
+    #[test]
+    fn fdt_node_walkers() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("chosen")
+            .end_child()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let chosen = fdt_root.first_child().unwrap();
+        assert!(chosen.read_property("compatible\0".as_ptr()).is_err());
+
+        let hypervisor = chosen.next_sibling().unwrap();
+        assert_eq!(hypervisor.depth(), fdt_root.depth() + 1);
+        assert!(hypervisor.read_property("compatible\0".as_ptr()).is_ok());
+        assert!(hypervisor.next_sibling().is_none());
+
+        let vm1 = hypervisor.first_child().unwrap();
+        assert_eq!(vm1.depth(), fdt_root.depth() + 2);
+        assert_eq!(
+            vm1.read_property("debug_name\0".as_ptr()).unwrap(),
+            b"primary_vm\0"
+        );
+        assert!(vm1.first_child().is_none());
+        assert!(vm1.next_sibling().is_none());
+
+        // Nodes handed to C and back keep their place in the tree.
+        let vm1 = FdtNode::from(fdt_node::from(vm1));
+        assert_eq!(vm1.depth(), fdt_root.depth() + 2);
+        assert_eq!(
+            vm1.read_property("debug_name\0".as_ptr()).unwrap(),
+            b"primary_vm\0"
+        );
+    }
+
+    #[test]
+    fn unknown_hypervisor_child() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("config")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms.len(), 2);
+        assert_eq!(
+            m.init_strict(&fdt_root).unwrap_err(),
+            Error::UnknownHypervisorChild
+        );
+
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init_strict(&fdt_root).unwrap();
+
+        assert!(is_vm_node_name(b"vm12\0"));
+        assert!(!is_vm_node_name(b"vmm1\0"));
+        assert!(!is_vm_node_name(b"vm01\0"));
+        assert!(!is_vm_node_name(b"vm\0"));
+    }
+
+    #[test]
+    fn find_child_ci() {
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+
+        let mut node = fdt_root.clone();
+        assert!(node.find_child_ci("HyperVisor").is_some());
+        assert!(node.find_child_ci("VM2").is_some());
+        assert_eq!(
+            node.read_property("debug_name\0".as_ptr()).unwrap(),
+            b"first_secondary_vm\0"
+        );
+
+        let mut node = fdt_root.clone();
+        assert!(node.find_child_ci("hyper").is_none());
+    }
+
+    #[test]
+    fn reserved_property() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .integer_property("phandle", 1)
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms.len(), 1);
+        assert_eq!(as_asciz(&m.vms[0].debug_name), b"primary_vm");
+
+        assert_eq!(
+            m.init_strict(&fdt_root).unwrap_err(),
+            Error::ReservedProperty
+        );
+    }
+
+    #[test]
+    fn all_ones_mem_size() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .integer_list_property("mem_size", &[0xffff_ffff, 0xffff_ffff])
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].mem_size, u64::max_value());
+
+        assert_eq!(
+            m.init_strict(&fdt_root).unwrap_err(),
+            Error::MalformedInteger
+        );
+    }
+
+    #[test]
+    fn property_order_independence() {
+        let canonical = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(2)
+            .mem_size(0x2000)
+            .kernel_filename("kernel")
+            .boot_address(0x8000_0000)
+            .end_child()
+            .end_child()
+            .build();
+        let reordered = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .boot_address(0x8000_0000)
+            .kernel_filename("kernel")
+            .mem_size(0x2000)
+            .vcpu_count(2)
+            .debug_name("secondary_vm")
+            .end_child()
+            .end_child()
+            .build();
+        assert_ne!(canonical, reordered);
+
+        let fdt_root = get_fdt_root(&canonical).unwrap();
+        let mut expected = Manifest::new();
+        expected.init(&fdt_root).unwrap();
+
+        let fdt_root = get_fdt_root(&reordered).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[..], expected.vms[..]);
+    }
+
+    #[test]
+    fn vm_reg() {
+        fn gen_reg_dtb(cells: Option<(u64, u64)>, reg: &[u64], mem_size: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder.start_child("hypervisor").compatible_hafnium();
+            if let Some((address_cells, size_cells)) = cells {
+                builder
+                    .integer_property("#address-cells", address_cells)
+                    .integer_property("#size-cells", size_cells);
+            }
+            builder
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .kernel_filename("kernel")
+                .integer_list_property("reg", reg);
+            if let Some(mem_size) = mem_size {
+                builder.mem_size(mem_size);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        // Without cell counts, addresses take two cells and sizes one.
+        let dtb = gen_reg_dtb(None, &[0x1, 0x8000_0000, 0x20_0000], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].boot_address, Some(0x1_8000_0000));
+        assert_eq!(m.vms[1].mem_size, 0x20_0000);
+
+        let dtb = gen_reg_dtb(Some((1, 1)), &[0x8000_0000, 0x20_0000], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].boot_address, Some(0x8000_0000));
+        assert_eq!(m.vms[1].mem_size, 0x20_0000);
+
+        let dtb = gen_reg_dtb(Some((2, 2)), &[0x1, 0x8000_0000, 0x1, 0x0], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].boot_address, Some(0x1_8000_0000));
+        assert_eq!(m.vms[1].mem_size, 0x1_0000_0000);
+
+        // The `reg` must match the cell counts.
+        let dtb = gen_reg_dtb(None, &[0x1, 0x8000_0000, 0x0, 0x20_0000], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+
+        let dtb = gen_reg_dtb(None, &[0x1, 0x8000_0000, 0x20_0000], Some(0x20_0000));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
+    }
+
+    #[test]
+    fn interrupt_controller() {
+        fn gen_interrupt_parent_dtb(interrupt_parent: u64) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("interrupt-controller@8000000")
+                .integer_property("phandle", 1)
+                .end_child()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel")
+                .integer_property("interrupt-parent", interrupt_parent)
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_interrupt_parent_dtb(1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(as_asciz(&m.vms[0].interrupt_controller), b"");
+        assert_eq!(
+            as_asciz(&m.vms[1].interrupt_controller),
+            b"interrupt-controller@8000000"
+        );
+
+        let dtb = gen_interrupt_parent_dtb(2);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::UnresolvedPhandle);
+    }
+
+    #[test]
+    fn interrupts_extended() {
+        fn gen_interrupts_extended_dtb(
+            interrupts_extended: &[u64],
+            interrupts: Option<&[u64]>,
+        ) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("gic@8000000")
+                .integer_property("phandle", 1)
+                .integer_property("#interrupt-cells", 3)
+                .end_child()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_list_property("interrupts-extended", interrupts_extended);
+            if let Some(interrupts) = interrupts {
+                builder.integer_list_property("interrupts", interrupts);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let dtb = gen_interrupts_extended_dtb(&[1, 0, 10, 4, 1, 1, 11, 1], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(as_asciz(&m.vms[0].interrupt_controller), b"gic@8000000");
+        assert_eq!(
+            &m.vms[0].interrupts[..],
+            &[
+                Interrupt {
+                    id: 42,
+                    attributes: 4
+                },
+                Interrupt {
+                    id: 27,
+                    attributes: 1
+                },
+            ]
+        );
+
+        // The second specifier is one cell short.
+        let dtb = gen_interrupts_extended_dtb(&[1, 0, 10, 4, 1, 1, 11], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+
+        let dtb = gen_interrupts_extended_dtb(&[2, 0, 10, 4], None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::UnresolvedPhandle);
+
+        let dtb = gen_interrupts_extended_dtb(&[1, 0, 10, 4], Some(&[10, 4]));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
+    }
+
+    #[test]
+    fn all_interrupts() {
+        fn gen_interrupts_dtb(interrupts: &[u64]) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_list_property("interrupts", interrupts)
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_interrupts_dtb(&[10, 4, 11, 1, 10, 4]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[0].interrupts.len(), 3);
+        assert_eq!(
+            m.vms[0].all_interrupts().unwrap().collect::<Vec<_>>(),
+            [(10, 4), (11, 1)]
+        );
+
+        let dtb = gen_interrupts_dtb(&[10, 4, 11, 1, 10, 1]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].all_interrupts().err(),
+            Some(Error::DuplicateInterrupt)
+        );
+
+        fn gen_device_interrupts_dtb(attributes: u64, interrupts: &[u64]) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_list_property("interrupts", &[10, 4, 11, 1])
+                .start_child("memory-regions")
+                .start_child("uart")
+                .integer_property("base-address", 0x900_0000)
+                .integer_property("pages-count", 1)
+                .integer_property("attributes", attributes)
+                .integer_list_property("interrupts", interrupts)
+                .end_child()
+                .end_child()
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let device = u64::from(MEMORY_REGION_READ | MEMORY_REGION_WRITE | MEMORY_REGION_DEVICE);
+        let dtb = gen_device_interrupts_dtb(device, &[33, 4, 11, 1]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[0].interrupts.len(), 2);
+        assert_eq!(m.vms[0].memory_regions[0].interrupts.len(), 2);
+        assert_eq!(
+            m.vms[0].all_interrupts().unwrap().collect::<Vec<_>>(),
+            [(10, 4), (11, 1), (33, 4)]
+        );
+
+        let dtb = gen_device_interrupts_dtb(device, &[11, 4]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].all_interrupts().err(),
+            Some(Error::DuplicateInterrupt)
+        );
+
+        // Only devices raise interrupts.
+        let dtb = gen_device_interrupts_dtb(u64::from(MEMORY_REGION_READ), &[33, 4]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
+    }
+
+    #[test]
+    fn interrupt_cells() {
+        fn gen_interrupts_dtb(interrupt_cells: u64, interrupts: &[u64]) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("interrupt-controller@8000000")
+                .integer_property("phandle", 1)
+                .integer_property("#interrupt-cells", interrupt_cells)
+                .end_child()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_property("interrupt-parent", 1)
+                .integer_list_property("interrupts", interrupts)
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_interrupts_dtb(2, &[10, 4, 11, 1]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].interrupts[..],
+            [
+                Interrupt {
+                    id: 10,
+                    attributes: 4
+                },
+                Interrupt {
+                    id: 11,
+                    attributes: 1
+                },
+            ]
+        );
+
+        let dtb = gen_interrupts_dtb(3, &[0, 5, 4, 1, 3, 8]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].interrupts[..],
+            [
+                Interrupt {
+                    id: 37,
+                    attributes: 4
+                },
+                Interrupt {
+                    id: 19,
+                    attributes: 8
+                },
+            ]
+        );
+
+        let dtb = gen_interrupts_dtb(3, &[10, 4, 11, 1]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+    }
+
+    #[test]
+    fn reserved_vm_id() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm0")
+            .debug_name("reserved_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ReservedVmId(0));
+    }
+
+    #[test]
+    fn reserved_vm_id_override() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm0")
+            .debug_name("reserved_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init_with_reserved_override(&fdt_root, &[0]).unwrap();
+        assert_eq!(m.primary().unwrap().vm_id, HF_PRIMARY_VM_ID);
+        assert_eq!(m.vms.len(), 2);
+        assert_eq!(as_asciz(&m.get_vm(0).unwrap().debug_name), b"reserved_vm");
+
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ReservedVmId(0));
+    }
+
+    #[test]
+    fn primary_kernel_filename() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::UnexpectedProperty);
+    }
+
+    #[test]
+    fn kernel_boot_params() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .integer_list_property("kernel-boot-params", &[0x0102_0300, 0xffff_ffff])
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[0].kernel_boot_params.is_none());
+        let params = m.vms[1].kernel_boot_params.as_ref().unwrap();
+        assert_eq!(&params[..], &[1, 2, 3, 0, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn kernel_boot_params_too_large() {
+        let params = [0; MAX_KERNEL_BOOT_PARAMS_LENGTH / 4 + 1];
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .integer_list_property("kernel-boot-params", &params)
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::PropertyTooLarge);
+    }
+
+    #[test]
+    fn primary_ramdisk_filename() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .ramdisk_filename("initrd.img")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(as_asciz(&m.vms[0].ramdisk_filename), b"initrd.img");
+        assert_eq!(as_asciz(&m.vms[0].kernel_filename), b"");
+    }
+
+    #[test]
+    fn watchdog_timeout() {
+        fn gen_watchdog_timeout_dtb(timeout: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(timeout) = timeout {
+                builder.integer_property("watchdog-timeout-ms", timeout);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let dtb = gen_watchdog_timeout_dtb(Some(500));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[0].watchdog_timeout_ms, None);
+        assert_eq!(m.vms[1].watchdog_timeout_ms, Some(500));
+
+        let dtb = gen_watchdog_timeout_dtb(Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+
+        let dtb = gen_watchdog_timeout_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].watchdog_timeout_ms, None);
+    }
+
+    #[test]
+    fn duplicate_debug_name() {
+        fn gen_debug_name_dtb(name: &str) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name(name)
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("first_kernel")
+                .end_child()
+                .start_child("vm3")
+                .debug_name(name)
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("second_kernel")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_debug_name_dtb("");
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms.len(), 3);
+
+        let dtb = gen_debug_name_dtb("x");
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::DuplicateDebugName);
+    }
+
+    #[test]
+    fn vcpu_count_limit() {
+        fn gen_vcpu_count_limit_dtb(vcpu_count: u64) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(vcpu_count)
+                .mem_size(0x1000)
+                .kernel_filename("kernel")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb_last_valid = gen_vcpu_count_limit_dtb(MAX_CPUS as u64);
+        let dtb_too_many_cpus = gen_vcpu_count_limit_dtb(MAX_CPUS as u64 + 1);
+        let dtb_first_invalid = gen_vcpu_count_limit_dtb(u16::max_value() as u64 + 1);
+
+        let fdt_root = get_fdt_root(&dtb_last_valid).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms.len(), 2);
+        assert_eq!(m.vms[1].vcpu_count as usize, MAX_CPUS);
+
+        let fdt_root = get_fdt_root(&dtb_too_many_cpus).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::VcpuCountExceedsCpus(2)
+        );
+
+        let fdt_root = get_fdt_root(&dtb_first_invalid).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::IntegerOverflow);
+    }
+
+    #[test]
+    fn reserved_memory() {
+        fn gen_reserved_memory_dtb(second_base: u64) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .start_child("reserved-memory")
+                .integer_property("#address-cells", 2)
+                .integer_property("#size-cells", 1)
+                .start_child("region@80000000")
+                .integer_list_property("reg", &[0, 0x8000_0000, 0x2000])
+                .boolean_property("no-map")
+                .end_child()
+                .start_child("region@90000000")
+                .integer_list_property("reg", &[0, second_base, 0x1000])
+                .boolean_property("reusable")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_reserved_memory_dtb(0x9000_0000);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            &m.reserved_memory[..],
+            &[
+                ReservedMemory {
+                    base: 0x8000_0000,
+                    size: 0x2000,
+                    no_map: true,
+                    reusable: false,
+                    name: Default::default(),
+                },
+                ReservedMemory {
+                    base: 0x9000_0000,
+                    size: 0x1000,
+                    no_map: false,
+                    reusable: true,
+                    name: Default::default(),
+                },
+            ]
+        );
+
+        let dtb = gen_reserved_memory_dtb(0x8000_1000);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::MemoryRegionOverlap(RESERVED_MEMORY_OWNER, RESERVED_MEMORY_OWNER)
+        );
+    }
+
+    #[test]
+    fn bootargs() {
+        fn gen_bootargs_dtb(bootargs: Option<&str>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .start_child("chosen");
+            if let Some(bootargs) = bootargs {
+                builder.string_property("bootargs", bootargs);
+            }
+            builder.end_child().build()
+        }
+
+        let dtb = gen_bootargs_dtb(Some("console=ttyS0"));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(&m.bootargs.as_ref().unwrap()[..], b"console=ttyS0");
+
+        let dtb = gen_bootargs_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.bootargs.is_none());
+
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.bootargs.is_none());
+
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .start_child("chosen")
+            .integer_property("bootargs", 0x6869_6869)
+            .end_child()
+            .build();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+    }
+
+    #[test]
+    fn reg_names() {
+        // An empty list of names gives an empty `reg-names` property.
+        fn gen_reg_names_dtb(reg_names: &[&str]) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .start_child("reserved-memory")
+                .integer_property("#address-cells", 2)
+                .integer_property("#size-cells", 1)
+                .start_child("region@80000000")
+                .integer_list_property("reg", &[0, 0x8000_0000, 0x1000, 0, 0x9000_0000, 0x2000]);
+            if reg_names.is_empty() {
+                builder.boolean_property("reg-names");
+            } else {
+                builder.string_list_property("reg-names", reg_names);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let dtb = gen_reg_names_dtb(&["firmware", "log"]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.reserved_memory.len(), 2);
+        assert_eq!(m.reserved_memory[0].base, 0x8000_0000);
+        assert_eq!(as_asciz(&m.reserved_memory[0].name), b"firmware");
+        assert_eq!(m.reserved_memory[1].base, 0x9000_0000);
+        assert_eq!(as_asciz(&m.reserved_memory[1].name), b"log");
+
+        let dtb = gen_reg_names_dtb(&["firmware"]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+
+        let dtb = gen_reg_names_dtb(&["firmware", "log", "extra"]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+
+        let dtb = gen_reg_names_dtb(&[]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+    }
+
+    fn gen_valid_dtb() -> Vec<u8> {
+        ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm3")
+            .debug_name("second_secondary_vm")
+            .vcpu_count(3)
+            .mem_size(0x12345)
+            .kernel_filename("second_kernel")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("first_secondary_vm")
+            .vcpu_count(2)
+            .mem_size(12345)
+            .kernel_filename("first_kernel")
+            .end_child()
+            .end_child()
+            .build()
+    }
+
+    #[test]
+    fn valid() {
+        let dtb = gen_valid_dtb();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms.len(), 3);
+
+        let vm = &m.vms[0];
+        assert_eq!(vm.vm_id, HF_PRIMARY_VM_ID);
+        assert_eq!(as_asciz(&vm.debug_name), b"primary_vm");
+
+        let vm = &m.vms[1];
+        assert_eq!(vm.vm_id, 2);
+        assert_eq!(as_asciz(&vm.debug_name), b"first_secondary_vm");
+        assert_eq!(vm.vcpu_count, 2);
+        assert_eq!(vm.mem_size, 12345);
+        assert_eq!(as_asciz(&vm.kernel_filename), b"first_kernel");
+
+        let vm = &m.vms[2];
+        assert_eq!(vm.vm_id, 3);
+        assert_eq!(as_asciz(&vm.debug_name), b"second_secondary_vm");
+        assert_eq!(vm.vcpu_count, 3);
+        assert_eq!(vm.mem_size, 0x12345);
+        assert_eq!(as_asciz(&vm.kernel_filename), b"second_kernel");
+    }
+
+    #[test]
+    fn init_skip_malformed() {
+        fn gen_skip_malformed_dtb(primary_time_slice_ms: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm");
+            if let Some(time_slice_ms) = primary_time_slice_ms {
+                builder.integer_property("time-slice-ms", time_slice_ms);
+            }
+            builder.end_child();
+
+            // The second secondary VM has a 96-bit `mem_size`.
+            for (i, mem_size) in [&[0x1000][..], &[0, 0, 0x1000], &[0x1000]]
+                .iter()
+                .enumerate()
+            {
+                builder
+                    .start_child(&format!("vm{}", i + 2))
+                    .debug_name(&format!("secondary_vm{}", i + 2))
+                    .vcpu_count(1)
+                    .integer_list_property("mem_size", mem_size)
+                    .kernel_filename("kernel")
+                    .end_child();
+            }
+            builder.end_child().build()
+        }
+
+        let dtb = gen_skip_malformed_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        let skipped = m.init_skip_malformed(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms.iter().map(|vm| vm.vm_id).collect::<Vec<_>>(),
+            [1, 2, 4]
+        );
+        assert_eq!(&skipped[..], &[(3, Error::MalformedInteger)]);
+
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+
+        // A malformed primary VM is still fatal.
+        let dtb = gen_skip_malformed_dtb(Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init_skip_malformed(&fdt_root).unwrap_err(),
+            Error::MalformedInteger
+        );
+    }
+
+    #[test]
+    fn vm_clone() {
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+
+        let mut vm = m.vms[2].clone();
+        assert_eq!(vm, m.vms[2]);
+        vm.mem_size += 1;
+        assert_ne!(vm, m.vms[2]);
+        assert_eq!(m.vms[2].mem_size, 0x12345);
+    }
+
+    #[test]
+    fn outlives_dtb() {
+        let mut m = Manifest::new();
+        {
+            let mut dtb = gen_valid_dtb();
+            let fdt_root = get_fdt_root(&dtb).unwrap();
+            m.init(&fdt_root).unwrap();
+
+            // Clobber the buffer before dropping it, so that any reference into it would show.
+            for byte in dtb.iter_mut() {
+                *byte = 0;
+            }
+        }
+
+        assert_eq!(as_asciz(&m.vms[0].debug_name), b"primary_vm");
+        assert_eq!(as_asciz(&m.vms[1].debug_name), b"first_secondary_vm");
+        assert_eq!(as_asciz(&m.vms[2].kernel_filename), b"second_kernel");
+
+        // Boot parameters are opaque bytes rather than a string, so check them with a blob of
+        // their own.
+        fn gen_boot_params_dtb() -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_list_property("kernel-boot-params", &[0x0102_0304])
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let mut m = Manifest::new();
+        {
+            let mut dtb = gen_boot_params_dtb();
+            let fdt_root = get_fdt_root(&dtb).unwrap();
+            m.init(&fdt_root).unwrap();
+
+            for byte in dtb.iter_mut() {
+                *byte = 0;
+            }
+        }
+
+        let dtb = gen_boot_params_dtb();
+        let mut expected = Manifest::new();
+        expected.init(&get_fdt_root(&dtb).unwrap()).unwrap();
+        drop(dtb);
+
+        assert_eq!(m.vms[..], expected.vms[..]);
+        assert!(format!("{:?}", m.vms[0]).contains("kernel_boot_params: Some([1, 2, 3, 4])"));
+    }
+
+    #[test]
+    fn vm_eq() {
+        let dtb1 = gen_valid_dtb();
+        let dtb2 = gen_valid_dtb();
+        assert_ne!(dtb1.as_ptr(), dtb2.as_ptr());
+
+        let fdt_root = get_fdt_root(&dtb1).unwrap();
+        let mut m1: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m1.init(&fdt_root).unwrap();
+
+        let fdt_root = get_fdt_root(&dtb2).unwrap();
+        let mut m2: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m2.init(&fdt_root).unwrap();
+
+        assert_eq!(m1.vms[..], m2.vms[..]);
+        assert_ne!(m1.vms[1], m2.vms[2]);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn stats() {
+        let dtb = gen_valid_dtb();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        assert_eq!(
+            m.init_with_stats(&fdt_root).unwrap(),
+            ParseStats {
+                // "hypervisor", "vm0" to "vm4", "chosen", "aliases" and "reserved-memory".
+                nodes_visited: 9,
+                // "compatible", then the properties of "vm1", "vm3" and "vm2".
+                bytes_read: 16 + 11 + 42 + 40,
+                vms_parsed: 3,
+            }
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn debug_name_cstr() {
+        use std::ffi::CStr;
+
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].debug_name_cstr().unwrap(),
+            CStr::from_bytes_with_nul(b"primary_vm\0").unwrap()
+        );
+        assert_eq!(
+            m.vms[2].debug_name_cstr().unwrap().to_bytes_with_nul(),
+            b"second_secondary_vm\0"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn validate_file() {
+        let dtb = gen_valid_dtb();
+        let path = std::env::temp_dir().join(format!("manifest-{}.dtb", std::process::id()));
+        std::fs::write(&path, &dtb).unwrap();
+
+        let m = host::validate_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(m.vms.len(), 3);
+        assert_eq!(as_asciz(&m.vms[1].kernel_filename), b"first_kernel");
+
+        assert_eq!(
+            host::validate_file(&path).unwrap_err(),
+            Error::InvalidArgument
+        );
+    }
+
+    #[cfg(all(feature = "std", unix))]
+    #[test]
+    fn mmap_dtb() {
+        let dtb = gen_valid_dtb();
+        let path = std::env::temp_dir().join(format!("manifest-mmap-{}.dtb", std::process::id()));
+        std::fs::write(&path, &dtb).unwrap();
+
+        let mapping = host::MmapDtb::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(unsafe { mapping.mem_iter().as_slice() }, &dtb[..]);
+
+        let mut m = Manifest::new();
+        m.init(&fdt_root(mapping.mem_iter()).unwrap()).unwrap();
+        assert_eq!(m.vms.len(), 3);
+
+        assert!(host::MmapDtb::open(&path).is_err());
+    }
+
+    #[test]
+    fn init_at_offset() {
+        let mut buffer = [0; 64].to_vec();
+        buffer.extend_from_slice(&gen_valid_dtb());
+        let buffer_it = unsafe { MemIter::from_raw(buffer.as_ptr(), buffer.len()) };
+
+        let mut m = Manifest::new();
+        m.init_at_offset(&buffer_it, 64).unwrap();
+        assert_eq!(m.vms.len(), 3);
+
+        assert_eq!(
+            m.init_at_offset(&buffer_it, 60).unwrap_err(),
+            Error::InvalidArgument
+        );
+        assert_eq!(
+            m.init_at_offset(&buffer_it, buffer.len() + 8).unwrap_err(),
+            Error::InvalidArgument
+        );
+        assert_eq!(
+            m.init_at_offset(&buffer_it, 0).unwrap_err(),
+            Error::CorruptedFdt
+        );
+    }
+
+    #[test]
+    fn init_with_resolver() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .end_child()
+            .end_child()
+            .build();
+
+        let path = b"/images/kernel.bin";
+        let resolver = |name: &MemIter| {
+            if name.matches("kernel") {
+                Some(unsafe { MemIter::from_raw(path.as_ptr(), path.len()) })
+            } else {
+                None
+            }
+        };
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init_with_resolver(&fdt_root, resolver).unwrap();
+        assert_eq!(as_asciz(&m.vms[1].kernel_filename), path);
+
+        assert_eq!(
+            m.init_with_resolver(&fdt_root, |_| None).unwrap_err(),
+            Error::UnresolvedKernel
+        );
+    }
+
+    #[test]
+    fn init_from_raw() {
+        let dtb = gen_valid_dtb();
+
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap();
+        assert_eq!(m.vms.len(), 3);
+
+        assert_eq!(
+            unsafe { m.init_from_raw(ptr::null(), dtb.len()) }.unwrap_err(),
+            Error::InvalidArgument
+        );
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), 0) }.unwrap_err(),
+            Error::InvalidArgument
+        );
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len() - 1) }.unwrap_err(),
+            Error::FdtTruncated
+        );
+    }
+
+    #[test]
+    fn truncated_fdt() {
+        let dtb = gen_valid_dtb();
+
+        let mut m = Manifest::new();
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len() - 4) }.unwrap_err(),
+            Error::FdtTruncated
+        );
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), mem::size_of::<FdtHeader>() - 4) }.unwrap_err(),
+            Error::FdtTruncated
+        );
+
+        // A blob of the right size whose header is wrong is corrupted rather than truncated.
+        let mut corrupted = dtb;
+        corrupted[0] ^= 0xff;
+        assert_eq!(
+            unsafe { m.init_from_raw(corrupted.as_ptr(), corrupted.len()) }.unwrap_err(),
+            Error::CorruptedFdt
+        );
+    }
+
+    #[test]
+    fn unsupported_fdt_version() {
+        let mut dtb = gen_valid_dtb();
+
+        // Claim compatibility only with readers of version 18 and later.
+        set_header_field(&mut dtb, HEADER_LAST_COMP_VERSION, 18);
+        let mut m = Manifest::new();
+        assert_eq!(
+            unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap_err(),
+            Error::UnsupportedFdtVersion
+        );
+
+        set_header_field(&mut dtb, HEADER_LAST_COMP_VERSION, 17);
+        unsafe { m.init_from_raw(dtb.as_ptr(), dtb.len()) }.unwrap();
+    }
+
+    #[test]
+    fn check() {
+        let dtb = gen_valid_dtb();
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+        assert_eq!(Manifest::check(&fdt), Ok(3));
+
+        let mut corrupted = dtb;
+        corrupted[0] ^= 0xff;
+        let fdt = unsafe { MemIter::from_raw(corrupted.as_ptr(), corrupted.len()) };
+        assert_eq!(Manifest::check(&fdt), Err(Error::CorruptedFdt));
+
+        // Each VM parses on its own, but `init` rejects them together.
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("primary_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("first_kernel")
+            .end_child()
+            .end_child()
+            .build();
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+        let mut m = Manifest::new();
+        assert_eq!(
+            m.init(&fdt_root(&fdt).unwrap()).unwrap_err(),
+            Error::DuplicateDebugName
+        );
+        assert_eq!(Manifest::check(&fdt), Err(Error::DuplicateDebugName));
+
+        // The parts of the manifest other than its VMs are checked as well.
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .start_child("reserved-memory")
+            .integer_property("#address-cells", 1)
+            .integer_property("#size-cells", 1)
+            .start_child("region@1000")
+            .integer_list_property("reg", &[0x1000, 0x2000])
+            .end_child()
+            .start_child("region@2000")
+            .integer_list_property("reg", &[0x2000, 0x1000])
+            .end_child()
+            .end_child()
+            .build();
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+        assert_eq!(
+            Manifest::check(&fdt),
+            Err(Error::MemoryRegionOverlap(
+                RESERVED_MEMORY_OWNER,
+                RESERVED_MEMORY_OWNER
+            ))
+        );
+    }
+
+    #[test]
+    fn init_from_fdt() {
+        let dtb = gen_valid_dtb();
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+        let mut m = Manifest::new();
+        m.init_from_fdt(&fdt).unwrap();
+
+        let mut expected = Manifest::new();
+        expected.init(&get_fdt_root(&dtb).unwrap()).unwrap();
+        assert_eq!(&m.vms[..], &expected.vms[..]);
+
+        let mut corrupted = dtb;
+        corrupted[0] ^= 0xff;
+        let fdt = unsafe { MemIter::from_raw(corrupted.as_ptr(), corrupted.len()) };
+        assert_eq!(m.init_from_fdt(&fdt).unwrap_err(), Error::CorruptedFdt);
+    }
+
+    #[test]
+    fn reinit_if_changed() {
+        let mut dtb = gen_valid_dtb();
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+
+        let mut m = Manifest::new();
+        assert_eq!(m.reinit_if_changed(&fdt), Ok(true));
+        assert_eq!(m.reinit_if_changed(&fdt), Ok(false));
+
+        let pos = dtb
+            .windows(b"primary_vm".len())
+            .position(|w| w == b"primary_vm")
+            .unwrap();
+        dtb[pos..pos + b"primary_vm".len()].copy_from_slice(b"primary_VM");
+        let fdt = unsafe { MemIter::from_raw(dtb.as_ptr(), dtb.len()) };
+        assert_eq!(m.reinit_if_changed(&fdt), Ok(true));
+        assert_eq!(as_asciz(&m.vms[0].debug_name), b"primary_VM");
+    }
+
+    #[test]
+    fn compatible_strings() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible(&["hafnium,hypervisor", "hafnium,hafnium"])
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.compatible.len(), 2);
+        assert_eq!(as_asciz(&m.compatible[0]), b"hafnium,hypervisor");
+        assert_eq!(as_asciz(&m.compatible[1]), b"hafnium,hafnium");
+
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible(&["a", "b", "c", "d", "hafnium,hafnium"])
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::TooManyCompatibleStrings
+        );
+    }
+
+    #[test]
+    fn cpu_frequency() {
+        fn gen_cpu_frequency_dtb(cpu_frequency: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder.start_child("hypervisor").compatible_hafnium();
+            if let Some(cpu_frequency) = cpu_frequency {
+                builder.integer_property("cpu-frequency", cpu_frequency);
+            }
+            builder
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_cpu_frequency_dtb(Some(1_000_000_000));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.cpu_frequency_hz, Some(1_000_000_000));
+
+        let dtb = gen_cpu_frequency_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.cpu_frequency_hz, None);
+
+        let dtb = gen_cpu_frequency_dtb(Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+    }
+
+    #[test]
+    fn stack_size() {
+        fn gen_stack_size_dtb(stack_size: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder.start_child("hypervisor").compatible_hafnium();
+            if let Some(stack_size) = stack_size {
+                builder.integer_property("stack-size", stack_size);
+            }
+            builder
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_stack_size_dtb(Some(4 * PAGE_SIZE as u64));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.stack_size, Some(4 * PAGE_SIZE as u64));
+
+        let dtb = gen_stack_size_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.stack_size, None);
+
+        let dtb = gen_stack_size_dtb(Some(PAGE_SIZE as u64 + 8));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MisalignedAddress);
+
+        let dtb = gen_stack_size_dtb(Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+    }
+
+    #[test]
+    fn index_by_vm_id() {
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+
+        assert_eq!(as_asciz(&m[HF_PRIMARY_VM_ID].debug_name), b"primary_vm");
+        assert_eq!(as_asciz(&m[3].debug_name), b"second_secondary_vm");
+        assert_eq!(m.get_vm(2).unwrap().vm_id, 2);
+        assert!(m.get_vm(4).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_by_missing_vm_id() {
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+
+        let _ = &m[4];
+    }
+
+    #[test]
+    fn max_cpus() {
+        fn gen_max_cpus_dtb(max_cpus: u64) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .integer_property("max-cpus", max_cpus)
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_max_cpus_dtb(MAX_CPUS as u64);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+
+        let dtb = gen_max_cpus_dtb(MAX_CPUS as u64 + 1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::CpuCountMismatch);
+    }
+
+    #[test]
+    fn version_range() {
+        fn gen_version_dtb(min_version: Option<u64>, max_version: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder.start_child("hypervisor").compatible_hafnium();
+            if let Some(min_version) = min_version {
+                builder.integer_property("hafnium,min-version", min_version);
+            }
+            if let Some(max_version) = max_version {
+                builder.integer_property("hafnium,max-version", max_version);
+            }
+            builder
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_version_dtb(Some(2), Some(4));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert!(m.is_compatible(2));
+        assert!(m.is_compatible(4));
+        assert!(!m.is_compatible(1));
+        assert!(!m.is_compatible(5));
+
+        let dtb = gen_version_dtb(Some(2), None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.is_compatible(u32::max_value()));
+        assert!(!m.is_compatible(1));
+
+        let dtb = gen_version_dtb(None, None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.is_compatible(0));
+
+        let dtb = gen_version_dtb(Some(4), Some(2));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+    }
+
+    #[test]
+    fn primary() {
+        let m = Manifest::new();
+        assert_eq!(m.primary().unwrap_err(), Error::NoPrimaryVm);
+
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(as_asciz(&m.primary().unwrap().debug_name), b"primary_vm");
+    }
+
+    #[test]
+    fn vm_summary() {
+        let dtb = gen_valid_dtb();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+
+        let summaries: Vec<_> = m.vms.iter().map(ManifestVm::summary).collect();
+        assert_eq!(
+            summaries,
+            [
+                VmSummary {
+                    id: HF_PRIMARY_VM_ID,
+                    vcpu_count: 0,
+                    mem_size: 0,
+                },
+                VmSummary {
+                    id: 2,
+                    vcpu_count: 2,
+                    mem_size: 12345,
+                },
+                VmSummary {
+                    id: 3,
+                    vcpu_count: 3,
+                    mem_size: 0x12345,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_kernels() {
+        let dtb = gen_valid_dtb();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+
+        let kernels: Vec<_> = m.iter_kernels().collect();
+        assert_eq!(
+            kernels,
+            [(2, &b"first_kernel"[..]), (3, &b"second_kernel"[..])]
+        );
+    }
+
+    #[test]
+    fn write_summary() {
+        let dtb = gen_valid_dtb();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+
+        let mut buf = ArrayString::<[u8; 256]>::new();
+        m.write_summary(&mut buf).unwrap();
+        assert_eq!(
+            buf.as_str(),
+            "VM 1: primary_vm, 0 vCPUs, 0x0 bytes\n\
+             VM 2: first_secondary_vm, 2 vCPUs, 0x3039 bytes\n\
+             VM 3: second_secondary_vm, 3 vCPUs, 0x12345 bytes\n"
+        );
+    }
+
+    #[test]
+    fn memory_regions() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x2000)
+            .kernel_filename("kernel")
+            .start_child("memory-regions")
+            .memory_region("rx", 0x9000_0000, 1)
+            .memory_region("tx", 0x9000_1000, 2)
+            .end_child()
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            &m.vms[1].memory_regions[..],
+            &[
+                MemoryRegion {
+                    base_address: 0x9000_0000,
+                    page_count: 1,
+                    attributes: 0,
+                    relative: false,
+                    interrupts: ArrayVec::new(),
+                },
+                MemoryRegion {
+                    base_address: 0x9000_1000,
+                    page_count: 2,
+                    attributes: 0,
+                    relative: false,
+                    interrupts: ArrayVec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_memory_region() {
+        fn gen_relative_region_dtb(boot_address: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x2000)
+                .kernel_filename("kernel");
+            if let Some(boot_address) = boot_address {
+                builder.boot_address(boot_address);
+            }
+            builder
+                .start_child("memory-regions")
+                .start_child("rx")
+                .integer_property("base-address", 0x10_0000)
+                .integer_property("pages-count", 1)
+                .integer_property("attributes", 0)
+                .boolean_property("relative-address")
+                .end_child()
+                .memory_region("tx", 0x9000_0000, 1)
+                .end_child()
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_relative_region_dtb(Some(0x8000_0000));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        let regions = &m.vms[1].memory_regions;
+        assert_eq!(regions[0].base_address, 0x8010_0000);
+        assert!(regions[0].relative);
+        assert_eq!(regions[1].base_address, 0x9000_0000);
+        assert!(!regions[1].relative);
+
+        let dtb = gen_relative_region_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
+    }
+
+    #[test]
+    fn memory_region_attributes() {
+        fn gen_attributes_dtb(attributes: u32) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .start_child("memory-regions")
+                .start_child("region")
+                .integer_property("base-address", 0x9000_0000)
+                .integer_property("pages-count", 1)
+                .integer_property("attributes", attributes as u64)
+                .end_child()
+                .end_child()
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_attributes_dtb(MEMORY_REGION_READ | MEMORY_REGION_WRITE);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].memory_regions[0].attributes,
+            MEMORY_REGION_READ | MEMORY_REGION_WRITE
+        );
+
+        let dtb = gen_attributes_dtb(MEMORY_REGION_WRITE | MEMORY_REGION_EXECUTE);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InvalidMemoryAttributes
+        );
+
+        let dtb = gen_attributes_dtb(MEMORY_REGION_READ | 1 << 8);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InvalidMemoryAttributes
+        );
+    }
+
+    #[test]
+    fn cross_vm_overlap() {
+        fn gen_overlap_dtb(second_boot_address: u64, region_base: u64) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("first_secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x2000)
+                .kernel_filename("first_kernel")
+                .boot_address(0x8000_0000)
+                .end_child()
+                .start_child("vm3")
+                .debug_name("second_secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x2000)
+                .kernel_filename("second_kernel")
+                .boot_address(second_boot_address)
+                .start_child("memory-regions")
+                .memory_region("shared", region_base, 1)
+                .end_child()
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_overlap_dtb(0x8000_2000, 0x9000_0000);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        m.validate().unwrap();
+
+        let dtb = gen_overlap_dtb(0x8000_1000, 0x9000_0000);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.validate().unwrap_err(), Error::MemoryRegionOverlap(2, 3));
+
+        let dtb = gen_overlap_dtb(0x8000_2000, 0x8000_1000);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.validate().unwrap_err(), Error::MemoryRegionOverlap(2, 3));
+    }
+
+    #[test]
+    fn memory_map() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .start_child("memory-regions")
+            .memory_region("shared", 0x9000_0000, 1)
+            .end_child()
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x2000)
+            .kernel_filename("kernel")
+            .boot_address(0x8000_0000)
+            .start_child("memory-regions")
+            .memory_region("rx", 0x8800_0000, 2)
+            .end_child()
+            .end_child()
+            .end_child()
+            .start_child("reserved-memory")
+            .integer_property("#address-cells", 2)
+            .integer_property("#size-cells", 1)
+            .start_child("region@70000000")
+            .integer_list_property("reg", &[0, 0x7000_0000, 0x1000])
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        m.validate().unwrap();
+        assert_eq!(
+            &m.memory_map()[..],
+            &[
+                (0x7000_0000, 0x7000_1000, RESERVED_MEMORY_OWNER),
+                (0x8000_0000, 0x8000_2000, 2),
+                (0x8800_0000, 0x8800_2000, 2),
+                (0x9000_0000, 0x9000_1000, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_high_watermark() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x4000)
+            .kernel_filename("kernel")
+            .boot_address(0x8000_0000)
+            .start_child("memory-regions")
+            .memory_region("rx", 0x7000_0000, 2)
+            .end_child()
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.memory_high_watermark(), 0x8000_4000);
+
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.memory_high_watermark(), 0);
+    }
+
+    #[test]
+    fn secure() {
+        fn gen_secure_dtb(secure: bool, exception_level: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("partition")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if secure {
+                builder.boolean_property("secure");
+            }
+            if let Some(exception_level) = exception_level {
+                builder.integer_property("exception-level", exception_level);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let dtb = gen_secure_dtb(true, Some(1));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert!(!m.vms[0].secure);
+        assert_eq!(m.vms[0].exception_level, None);
+        assert!(m.vms[1].secure);
+        assert_eq!(m.vms[1].exception_level, Some(ExceptionLevel::El1));
+
+        let dtb = gen_secure_dtb(false, None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(!m.vms[1].secure);
+        assert_eq!(m.vms[1].exception_level, None);
+
+        let dtb = gen_secure_dtb(true, None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InconsistentSecurityState
+        );
+
+        let dtb = gen_secure_dtb(false, Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InconsistentSecurityState
+        );
+    }
+
+    #[test]
+    fn largest_vm() {
+        let dtb = gen_valid_dtb();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.largest_vm().unwrap().vm_id, 3);
+
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.largest_vm().is_none());
+    }
+
+    #[test]
+    fn execution_contexts() {
+        fn gen_execution_contexts_dtb(
+            execution_ctx_count: u64,
+            secure: bool,
+            second_vcpu_count: u64,
+        ) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("composite_partition")
+                .vcpu_count(3)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if secure {
+                builder
+                    .boolean_property("secure")
+                    .integer_property("exception-level", 1);
+            }
+            builder
+                .integer_property("execution-ctx-count", execution_ctx_count)
+                .start_child("ctx@0")
+                .vcpu_count(2)
+                .integer_property("exception-level", 1)
+                .end_child()
+                .start_child("ctx@1")
+                .vcpu_count(second_vcpu_count)
+                .integer_property("exception-level", 0)
+                .end_child()
+                .end_child()
+                .end_child()
+                .build()
+        }
+
+        let dtb = gen_execution_contexts_dtb(3, true, 1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[0].execution_contexts.len(), 0);
+        assert_eq!(
+            &m.vms[1].execution_contexts[..],
+            &[
+                ExecutionContext {
+                    vcpu_count: 2,
+                    exception_level: Some(ExceptionLevel::El1),
+                },
+                ExecutionContext {
+                    vcpu_count: 1,
+                    exception_level: Some(ExceptionLevel::El0),
+                },
+            ]
+        );
+
+        let dtb = gen_execution_contexts_dtb(4, true, 1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InconsistentExecutionContexts
+        );
+
+        // Only secure partitions run their contexts at a given exception level.
+        let dtb = gen_execution_contexts_dtb(3, false, 1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InconsistentSecurityState
+        );
+
+        let dtb = gen_execution_contexts_dtb(2, true, 0);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+    }
+
+    #[test]
+    fn boot_info() {
+        fn gen_boot_info_dtb(gp_register: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .boolean_property("boot-info");
+            if let Some(gp_register) = gp_register {
+                builder.integer_property("gp-register-num", gp_register);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let dtb = gen_boot_info_dtb(Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[0].boot_info);
+        assert_eq!(m.vms[0].gp_register, Some(0));
 
-            // Start root node.
-            builder.start_child("/");
+        let dtb = gen_boot_info_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
+    }
+
+    #[test]
+    fn notification_count() {
+        fn gen_notification_dtb(support: bool, count: u64) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
             builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_property("notification-count", count);
+            if support {
+                builder.boolean_property("notification-support");
+            }
+            builder.end_child().end_child().build()
         }
 
-        fn build(&mut self) -> Vec<u8> {
-            self.end_child();
+        let dtb = gen_notification_dtb(true, MAX_NOTIFICATIONS as u64);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[0].notification_support);
+        assert_eq!(m.vms[0].notification_count, Some(MAX_NOTIFICATIONS));
 
-            let mut child = Command::new("../build/image/dtc.py")
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .args(&["compile"])
-                .spawn()
-                .unwrap();
+        let dtb = gen_notification_dtb(true, MAX_NOTIFICATIONS as u64 + 1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            m.init(&fdt_root).unwrap_err(),
+            Error::InvalidNotificationCount
+        );
 
-            child
-                .stdin
-                .as_mut()
-                .unwrap()
-                .write_all(self.dts.as_bytes())
-                .unwrap();
+        let dtb = gen_notification_dtb(false, 1);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
+    }
 
-            child.wait_with_output().unwrap().stdout
+    #[test]
+    fn uuids() {
+        fn gen_uuid_dtb(uuid: &[u64]) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_list_property("uuid", uuid)
+                .end_child()
+                .end_child()
+                .build()
         }
 
-        fn start_child(&mut self, name: &str) -> &mut Self {
-            self.dts.push_str(name);
-            self.dts.push_str(" {\n");
-            self
+        let dtb = gen_uuid_dtb(&[
+            1,
+            2,
+            3,
+            4,
+            0xb4b5_b6b7,
+            0xc4c5_c6c7,
+            0xd4d5_d6d7,
+            0xe4e5_e6e7,
+        ]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.vms[0].uuids[..],
+            [
+                [1, 2, 3, 4],
+                [0xb4b5_b6b7, 0xc4c5_c6c7, 0xd4d5_d6d7, 0xe4e5_e6e7]
+            ]
+        );
+
+        let dtb = gen_uuid_dtb(&[1, 2, 3]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+
+        let dtb = gen_uuid_dtb(&[0; 4 * (MAX_UUIDS + 1)]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::TooManyUuids);
+    }
+
+    #[test]
+    fn read_property_array() {
+        let dtb = ManifestDtBuilder::new()
+            .integer_list_property("four", &[1, 2, 3, 0xffff_ffff])
+            .integer_list_property("three", &[1, 2, 3])
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(
+            fdt_root.read_property_array::<[u32; 4]>("four\0".as_ptr()),
+            Ok([1, 2, 3, 0xffff_ffff])
+        );
+        assert_eq!(
+            fdt_root.read_property_array::<[u32; 4]>("three\0".as_ptr()),
+            Err(Error::MalformedInteger)
+        );
+        assert_eq!(
+            fdt_root.read_property_array::<[u32; 4]>("missing\0".as_ptr()),
+            Err(Error::PropertyNotFound)
+        );
+    }
+
+    #[test]
+    fn find_vm_by_uuid() {
+        let dtb = ManifestDtBuilder::new()
+            .start_child("hypervisor")
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child()
+            .start_child("vm2")
+            .debug_name("secondary_vm")
+            .vcpu_count(1)
+            .mem_size(0x1000)
+            .kernel_filename("kernel")
+            .integer_list_property("uuid", &[1, 2, 3, 4, 5, 6, 7, 8])
+            .end_child()
+            .end_child()
+            .build();
+
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.find_vm_by_uuid(&[5, 6, 7, 8]).unwrap().vm_id, 2);
+        assert!(m.find_vm_by_uuid(&[1, 2, 3, 5]).is_none());
+    }
+
+    #[test]
+    fn find_vm_by_alias() {
+        // No path gives an empty `partition` property.
+        fn gen_aliases_dtb(path: Option<&str>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder.start_child("aliases");
+            match path {
+                Some(path) => builder.string_property("partition", path),
+                None => builder.boolean_property("partition"),
+            };
+            builder
+                .string_property("hyp", "/hypervisor")
+                .end_child()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel")
+                .end_child()
+                .end_child()
+                .build()
         }
 
-        fn end_child(&mut self) -> &mut Self {
-            self.dts.push_str("};\n");
-            self
+        let dtb = gen_aliases_dtb(Some("/hypervisor/vm2"));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.aliases.len(), 1);
+        assert_eq!(m.find_vm_by_name("partition").unwrap().vm_id, 2);
+        assert_eq!(m.find_vm_by_name("primary_vm").unwrap().vm_id, 1);
+        assert!(m.find_vm_by_name("hyp").is_none());
+
+        let dtb = gen_aliases_dtb(Some("/hypervisor/vm3"));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::UnresolvedAlias);
+
+        let dtb = gen_aliases_dtb(Some(""));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+
+        let dtb = gen_aliases_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+    }
+
+    #[test]
+    fn validate_all() {
+        let platform = PlatformLimits {
+            total_ram: 0x10_0000,
+            cpu_count: MAX_CPUS,
+            max_vcpus_per_cpu: 5,
+        };
+
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        m.validate_all(&platform).unwrap();
+
+        assert_eq!(
+            m.validate_all(&PlatformLimits {
+                cpu_count: 2,
+                ..platform
+            })
+            .unwrap_err(),
+            Error::VcpuCountExceedsCpus(3)
+        );
+
+        // Neither secondary VM has an affinity, so each CPU may run all of their 5 vCPUs.
+        assert_eq!(
+            m.validate_all(&PlatformLimits {
+                max_vcpus_per_cpu: 4,
+                ..platform
+            })
+            .unwrap_err(),
+            Error::CpuOversubscribed(0)
+        );
+
+        // Keeping the VMs apart brings each CPU within the limit, as long as the CPUs exist.
+        m.vms[1].cpu_affinity = Some(0b01);
+        m.vms[2].cpu_affinity = Some(0b10);
+        let small = PlatformLimits {
+            cpu_count: 3,
+            max_vcpus_per_cpu: 3,
+            ..platform
+        };
+        m.validate_all(&small).unwrap();
+        m.vms[1].cpu_affinity = Some(0b1001);
+        assert_eq!(m.validate_all(&small).unwrap_err(), Error::InvalidCpuId(2));
+        m.vms[1].cpu_affinity = Some(0b10);
+        assert_eq!(
+            m.validate_all(&small).unwrap_err(),
+            Error::CpuOversubscribed(1)
+        );
+        m.vms[1].cpu_affinity = None;
+        assert_eq!(
+            m.validate_all(&PlatformLimits {
+                total_ram: 0x1_0000,
+                ..platform
+            })
+            .unwrap_err(),
+            Error::InsufficientMemory
+        );
+    }
+
+    #[test]
+    fn unique_uuids() {
+        fn gen_uuids_dtb(first_uuid: &[u64], second_uuid: &[u64]) -> Vec<u8> {
+            ManifestDtBuilder::new()
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .integer_list_property("uuid", &[0, 0, 0, 0])
+                .end_child()
+                .start_child("vm2")
+                .debug_name("first_partition")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("first_kernel")
+                .integer_list_property("uuid", first_uuid)
+                .end_child()
+                .start_child("vm3")
+                .debug_name("second_partition")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("second_kernel")
+                .integer_list_property("uuid", second_uuid)
+                .end_child()
+                .end_child()
+                .build()
         }
 
-        fn compatible(&mut self, value: &[&str]) -> &mut Self {
-            self.string_list_property("compatible", value)
+        let dtb = gen_uuids_dtb(&[1, 2, 3, 4], &[5, 6, 7, 8]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        m.validate_unique_uuids().unwrap();
+
+        let dtb = gen_uuids_dtb(&[0, 0, 0, 0], &[0, 0, 0, 0]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        m.validate_unique_uuids().unwrap();
+
+        let dtb = gen_uuids_dtb(&[1, 2, 3, 4], &[5, 6, 7, 8, 1, 2, 3, 4]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.validate_unique_uuids().unwrap_err(),
+            Error::DuplicateUuid(2, 3)
+        );
+    }
+
+    #[test]
+    fn log_level() {
+        fn gen_log_level_dtb(log_level: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm");
+            if let Some(log_level) = log_level {
+                builder.integer_property("log-level", log_level);
+            }
+            builder.end_child().end_child().build()
         }
 
-        fn compatible_hafnium(&mut self) -> &mut Self {
-            self.compatible(&["hafnium,hafnium"])
+        let dtb = gen_log_level_dtb(Some(MAX_LOG_LEVEL as u64));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[0].log_level, MAX_LOG_LEVEL);
+
+        let dtb = gen_log_level_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[0].log_level, DEFAULT_LOG_LEVEL);
+
+        for &level in [MAX_LOG_LEVEL as u64 + 1, 0x100].iter() {
+            let dtb = gen_log_level_dtb(Some(level));
+            let fdt_root = get_fdt_root(&dtb).unwrap();
+            assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
         }
+    }
 
-        fn debug_name(&mut self, value: &str) -> &mut Self {
-            self.string_property("debug_name", value)
+    #[test]
+    fn load_flags() {
+        fn gen_load_flags_dtb(load_flags: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(load_flags) = load_flags {
+                builder.integer_property("load-flags", load_flags);
+            }
+            builder.end_child().end_child().build()
         }
 
-        fn kernel_filename(&mut self, value: &str) -> &mut Self {
-            self.string_property("kernel_filename", value)
+        let dtb = gen_load_flags_dtb(Some(LOAD_FLAG_VERIFY_SIGNATURE as u64));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[1].verify_signature());
+        assert!(!m.vms[1].decrypt());
+
+        let dtb = gen_load_flags_dtb(Some(LOAD_FLAGS_MASK as u64));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[1].verify_signature());
+        assert!(m.vms[1].decrypt());
+
+        let dtb = gen_load_flags_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].load_flags, 0);
+
+        let dtb = gen_load_flags_dtb(Some(1 << 31));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
+    }
+
+    #[test]
+    fn memiter_errors() {
+        let message = |e: MemIterError| -> &str { Error::from(e).into() };
+
+        let name = b"primary_vm";
+        let it = unsafe { MemIter::from_raw(name.as_ptr(), name.len()) };
+        let e = it.copy_to(&mut [0; 4]).unwrap_err();
+        assert_eq!(Error::from(e), Error::BufferTooSmall);
+        assert_eq!(message(e), "Buffer too small");
+
+        let e = it.chunks(3).err().unwrap();
+        assert_eq!(Error::from(e), Error::MalformedInteger);
+        assert_eq!(message(e), "Malformed integer property");
+
+        let parse = |s: &str| unsafe { MemIter::from_raw(s.as_ptr(), s.len()) }.parse_u64_decimal();
+        let e = parse("12a").unwrap_err();
+        assert_eq!(Error::from(e), Error::MalformedInteger);
+        assert_eq!(message(e), "Malformed integer property");
+        let e = parse("18446744073709551616").unwrap_err();
+        assert_eq!(Error::from(e), Error::IntegerOverflow);
+        assert_eq!(message(e), "Integer overflow");
+    }
+
+    #[test]
+    fn is_fatal() {
+        assert!(Error::CorruptedFdt.is_fatal());
+        assert!(Error::FdtTruncated.is_fatal());
+        assert!(Error::MalformedInteger.is_fatal());
+        assert!(!Error::TooManyVms.is_fatal());
+        assert!(!Error::NotCompatible.is_fatal());
+        assert!(!Error::MemoryRegionOverlap(1, 2).is_fatal());
+    }
+
+    #[test]
+    fn validate_vm() {
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        let vm = &mut m.vms[1];
+        vm.validate().unwrap();
+
+        vm.vcpu_count = MAX_CPUS as spci_vcpu_count_t + 1;
+        assert_eq!(vm.validate().unwrap_err(), Error::VcpuCountExceedsCpus(2));
+        vm.vcpu_count = 1;
+
+        vm.entry_offset = vm.mem_size;
+        assert_eq!(vm.validate().unwrap_err(), Error::InvalidArgument);
+        vm.entry_offset = 0;
+
+        vm.memory_regions.push(MemoryRegion {
+            base_address: 0x8000_0001,
+            page_count: 1,
+            attributes: MEMORY_REGION_READ,
+            relative: false,
+            interrupts: ArrayVec::new(),
+        });
+        assert_eq!(vm.validate().unwrap_err(), Error::MisalignedAddress);
+        vm.memory_regions.clear();
+
+        vm.notification_count = Some(1);
+        assert_eq!(vm.validate().unwrap_err(), Error::ConflictingProperties);
+    }
+
+    #[test]
+    fn kernel_kind() {
+        let dtb = gen_valid_dtb();
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        let vm = &mut m.vms[1];
+        assert_eq!(vm.kernel_kind(), KernelKind::Raw);
+
+        let kinds = [
+            ("vmlinux.elf", KernelKind::Elf),
+            ("kernel.ELF", KernelKind::Elf),
+            ("kernel.bin", KernelKind::Raw),
+            ("image.itb", KernelKind::Fit),
+            ("image.itb.gz", KernelKind::Raw),
+            ("elf", KernelKind::Raw),
+        ];
+        for &(filename, kind) in kinds.iter() {
+            vm.kernel_filename = Default::default();
+            vm.kernel_filename[..filename.len()].copy_from_slice(filename.as_bytes());
+            assert_eq!(vm.kernel_kind(), kind, "{}", filename);
         }
+    }
 
-        fn vcpu_count(&mut self, value: u64) -> &mut Self {
-            self.integer_property("vcpu_count", value)
+    #[test]
+    fn status() {
+        fn gen_status_dtb(status: Option<&str>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(status) = status {
+                builder.string_property("status", status);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let dtb = gen_status_dtb(Some("disabled"));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[0].enabled);
+        assert!(!m.vms[1].enabled);
+
+        let dtb = gen_status_dtb(Some("okay"));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[1].enabled);
+
+        let dtb = gen_status_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert!(m.vms[1].enabled);
+
+        let dtb = gen_status_dtb(Some("fail"));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+
+        let dtb = gen_status_dtb(Some(&"disabled".repeat(MANIFEST_MAX_STRING_LENGTH)));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
+    }
+
+    #[test]
+    fn cpu_affinity() {
+        fn gen_affinity_dtb(cpus: Option<&[u64]>, affinity_mask: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(cpus) = cpus {
+                builder.integer_list_property("cpus", cpus);
+            }
+            if let Some(affinity_mask) = affinity_mask {
+                builder.integer_property("affinity-mask", affinity_mask);
+            }
+            builder.end_child().end_child().build()
         }
 
-        fn mem_size(&mut self, value: u64) -> &mut Self {
-            self.integer_property("mem_size", value)
-        }
+        let dtb = gen_affinity_dtb(None, Some(0b101));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        let from_mask = m.vms[1].cpu_affinity;
+        assert_eq!(from_mask, Some(0b101));
 
-        fn string_property(&mut self, name: &str, value: &str) -> &mut Self {
-            write!(self.dts, "{} = \"{}\";\n", name, value).unwrap();
-            self
-        }
+        let dtb = gen_affinity_dtb(Some(&[0, 2]), None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].cpu_affinity, from_mask);
 
-        fn string_list_property(&mut self, name: &str, value: &[&str]) -> &mut Self {
-            write!(self.dts, "{} = \"", name).unwrap();
-            self.dts.push_str(&value.join("\", \""));
-            self.dts.push_str("\";\n");
-            self
-        }
+        let dtb = gen_affinity_dtb(None, None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].cpu_affinity, None);
 
-        fn integer_property(&mut self, name: &str, value: u64) -> &mut Self {
-            write!(self.dts, "{} = <{}>;\n", name, value).unwrap();
-            self
-        }
-    }
+        let dtb = gen_affinity_dtb(Some(&[0, 2]), Some(0b101));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ConflictingProperties);
 
-    fn get_fdt_root<'a>(dtb: &'a [u8]) -> Option<FdtNode<'a>> {
-        let fdt_header = unsafe { &*(dtb.as_ptr() as *const FdtHeader) };
+        let dtb = gen_affinity_dtb(Some(&[MAX_CPUS as u64]), None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::InvalidCpuId(2));
 
-        let mut node = FdtNode::new_root(fdt_header)?;
-        node.find_child("\0".as_ptr());
-        Some(node)
+        let dtb = gen_affinity_dtb(None, Some(1 << MAX_CPUS));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::InvalidCpuId(2));
     }
 
     #[test]
-    fn no_hypervisor_node() {
-        let dtb = ManifestDtBuilder::new().build();
+    fn psci_suspend_mask() {
+        fn gen_psci_suspend_mask_dtb(mask: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(mask) = mask {
+                builder.integer_property("psci-suspend-mask", mask);
+            }
+            builder.end_child().end_child().build()
+        }
 
+        let dtb = gen_psci_suspend_mask_dtb(Some(0b11));
         let fdt_root = get_fdt_root(&dtb).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::NoHypervisorFdtNode);
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].psci_suspend_mask, Some(0b11));
+
+        let dtb = gen_psci_suspend_mask_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].psci_suspend_mask, None);
     }
 
     #[test]
-    fn no_compatible_property() {
-        let dtb = ManifestDtBuilder::new()
+    fn total_vcpus_on_cpu() {
+        let mut builder = ManifestDtBuilder::new();
+        builder
             .start_child("hypervisor")
-            .end_child()
-            .build();
+            .compatible_hafnium()
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child();
+        for (i, (vcpu_count, cpus)) in [(2, &[0, 1][..]), (3, &[1]), (1, &[])].iter().enumerate() {
+            builder
+                .start_child(&format!("vm{}", i + 2))
+                .debug_name(&format!("secondary_vm{}", i + 2))
+                .vcpu_count(*vcpu_count)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if !cpus.is_empty() {
+                builder.integer_list_property("cpus", cpus);
+            }
+            builder.end_child();
+        }
+        let dtb = builder.end_child().build();
 
         let fdt_root = get_fdt_root(&dtb).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::PropertyNotFound);
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.total_vcpus_on_cpu(0), 3);
+        assert_eq!(m.total_vcpus_on_cpu(1), 6);
+        assert_eq!(m.total_vcpus_on_cpu(2), 1);
+        assert_eq!(m.total_vcpus_on_cpu(64), 1);
     }
 
     #[test]
-    fn not_compatible() {
-        let dtb = ManifestDtBuilder::new()
-            .start_child("hypervisor")
-            .compatible(&["foo,bar"])
-            .end_child()
-            .build();
+    fn restart_policy() {
+        fn gen_restart_policy_dtb(policy: Option<&str>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(policy) = policy {
+                builder.string_property("restart-policy", policy);
+            }
+            builder.end_child().end_child().build()
+        }
+
+        let mut m = Manifest::new();
+        let policies = [
+            (None, RestartPolicy::Never),
+            (Some("never"), RestartPolicy::Never),
+            (Some("on-failure"), RestartPolicy::OnFailure),
+            (Some("always"), RestartPolicy::Always),
+        ];
+        for &(policy, expected) in policies.iter() {
+            let dtb = gen_restart_policy_dtb(policy);
+            let fdt_root = get_fdt_root(&dtb).unwrap();
+            m.init(&fdt_root).unwrap();
+            assert_eq!(m.vms[1].restart_policy, expected);
+        }
 
+        let dtb = gen_restart_policy_dtb(Some("sometimes"));
         let fdt_root = get_fdt_root(&dtb).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::NotCompatible);
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedString);
     }
 
     #[test]
-    fn compatible_one_of_many() {
-        let dtb = ManifestDtBuilder::new()
-            .start_child("hypervisor")
-            .compatible(&["foo,bar", "hafnium,hafnium"])
-            .start_child("vm1")
-            .debug_name("primary")
-            .end_child()
-            .end_child()
-            .build();
+    fn time_slice() {
+        fn gen_time_slice_dtb(time_slice_ms: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(time_slice_ms) = time_slice_ms {
+                builder.integer_property("time-slice-ms", time_slice_ms);
+            }
+            builder.end_child().end_child().build()
+        }
 
+        let dtb = gen_time_slice_dtb(Some(10));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].time_slice_ms, Some(10));
+
+        let dtb = gen_time_slice_dtb(None);
         let fdt_root = get_fdt_root(&dtb).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
         m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].time_slice_ms, None);
+
+        let dtb = gen_time_slice_dtb(Some(0));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::MalformedInteger);
     }
 
-    #[test]
-    fn no_vm_nodes() {
-        let dtb = ManifestDtBuilder::new()
+    fn gen_boot_order_dtb(boot_orders: &[Option<u64>]) -> Vec<u8> {
+        let mut builder = ManifestDtBuilder::new();
+        builder
             .start_child("hypervisor")
             .compatible_hafnium()
-            .end_child()
-            .build();
+            .start_child("vm1")
+            .debug_name("primary_vm")
+            .end_child();
+        for (i, boot_order) in boot_orders.iter().enumerate() {
+            builder
+                .start_child(&format!("vm{}", i + 2))
+                .debug_name(&format!("secondary_vm{}", i + 2))
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(boot_order) = boot_order {
+                builder.integer_property("boot-order", *boot_order);
+            }
+            builder.end_child();
+        }
+        builder.end_child().build()
+    }
+
+    #[test]
+    fn boot_order_unique() {
+        let mut m = Manifest::new();
 
+        let dtb = gen_boot_order_dtb(&[None, None, None]);
         let fdt_root = get_fdt_root(&dtb).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::NoPrimaryVm);
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.validate_boot_order_unique(), Ok(()));
+
+        let dtb = gen_boot_order_dtb(&[Some(5), None, Some(1)]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].boot_order, Some(5));
+        assert_eq!(m.vms[2].boot_order, None);
+        assert_eq!(m.vms[3].boot_order, Some(1));
+        assert_eq!(m.validate_boot_order_unique(), Ok(()));
+
+        let dtb = gen_boot_order_dtb(&[Some(5), None, Some(5)]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(
+            m.validate_boot_order_unique(),
+            Err(Error::DuplicateBootOrder(2, 4))
+        );
+
+        // An explicit 0 is a boot order like any other.
+        let dtb = gen_boot_order_dtb(&[Some(0), None, Some(0)]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].boot_order, Some(0));
+        assert_eq!(
+            m.validate_boot_order_unique(),
+            Err(Error::DuplicateBootOrder(2, 4))
+        );
     }
 
     #[test]
-    fn long_string() {
-        fn gen_long_string_dtb(valid: bool) -> Vec<u8> {
-            const LAST_VALID: &'static str = "1234567890123456789012345678901";
-            const FIRST_INVALID: &'static str = "12345678901234567890123456789012";
-            assert_eq!(LAST_VALID.len() + 1, MANIFEST_MAX_STRING_LENGTH);
-            assert_eq!(FIRST_INVALID.len() + 1, MANIFEST_MAX_STRING_LENGTH + 1);
+    fn boot_sequence() {
+        let dtb = gen_boot_order_dtb(&[None, Some(7), None, Some(3)]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[2].boot_order, Some(7));
+        assert_eq!(m.vms[4].boot_order, Some(3));
+        assert_eq!(m.validate_boot_order_unique(), Ok(()));
+        assert_eq!(&m.boot_sequence()[..], &[5, 3, 2, 4]);
 
-            ManifestDtBuilder::new()
+        let dtb = gen_boot_order_dtb(&[None, None]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(&m.boot_sequence()[..], &[2, 3]);
+
+        let dtb = gen_boot_order_dtb(&[None, Some(1), Some(0)]);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.validate_boot_order_unique(), Ok(()));
+        assert_eq!(&m.boot_sequence()[..], &[4, 3, 2]);
+    }
+
+    #[test]
+    fn kernel_size() {
+        fn gen_kernel_size_dtb(kernel_size: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
                 .start_child("hypervisor")
                 .compatible_hafnium()
                 .start_child("vm1")
-                .debug_name(if valid { LAST_VALID } else { FIRST_INVALID })
-                .end_child()
+                .debug_name("primary_vm")
                 .end_child()
-                .build()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x10000)
+                .kernel_filename("kernel");
+            if let Some(kernel_size) = kernel_size {
+                builder.integer_property("kernel-size", kernel_size);
+            }
+            builder.end_child().end_child().build()
         }
 
-        let dtb_last_valid = gen_long_string_dtb(true);
-        let dtb_first_invalid = gen_long_string_dtb(false);
+        let dtb = gen_kernel_size_dtb(Some(0x8000));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].kernel_size, Some(0x8000));
 
-        let fdt_root = get_fdt_root(&dtb_last_valid).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
+        let dtb = gen_kernel_size_dtb(Some(0x10000));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
         m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].kernel_size, Some(0x10000));
 
-        let fdt_root = get_fdt_root(&dtb_first_invalid).unwrap();
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::StringTooLong);
+        let dtb = gen_kernel_size_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].kernel_size, None);
+
+        let dtb = gen_kernel_size_dtb(Some(0x10001));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::InvalidArgument);
     }
 
     #[test]
-    fn reserved_vm_id() {
-        let dtb = ManifestDtBuilder::new()
-            .start_child("hypervisor")
-            .compatible_hafnium()
-            .start_child("vm1")
-            .debug_name("primary_vm")
-            .end_child()
-            .start_child("vm0")
-            .debug_name("reserved_vm")
-            .vcpu_count(1)
-            .mem_size(0x1000)
-            .kernel_filename("kernel")
-            .end_child()
-            .end_child()
-            .build();
+    fn entry_offset() {
+        fn gen_entry_offset_dtb(entry_offset: Option<u64>) -> Vec<u8> {
+            let mut builder = ManifestDtBuilder::new();
+            builder
+                .start_child("hypervisor")
+                .compatible_hafnium()
+                .start_child("vm1")
+                .debug_name("primary_vm")
+                .end_child()
+                .start_child("vm2")
+                .debug_name("secondary_vm")
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel");
+            if let Some(entry_offset) = entry_offset {
+                builder.integer_property("entry-offset", entry_offset);
+            }
+            builder.end_child().end_child().build()
+        }
 
+        let dtb = gen_entry_offset_dtb(Some(0x800));
         let fdt_root = get_fdt_root(&dtb).unwrap();
-        let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::ReservedVmId);
+        let mut m = Manifest::new();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].entry_offset, 0x800);
+
+        let dtb = gen_entry_offset_dtb(None);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        m.init(&fdt_root).unwrap();
+        assert_eq!(m.vms[1].entry_offset, 0);
+
+        let dtb = gen_entry_offset_dtb(Some(0x1000));
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::InvalidArgument);
     }
 
     #[test]
-    fn vcpu_count_limit() {
-        fn gen_vcpu_count_limit_dtb(vcpu_count: u64) -> Vec<u8> {
+    fn explicit_vm_id() {
+        fn gen_vm_id_dtb(id: u64) -> Vec<u8> {
             ManifestDtBuilder::new()
                 .start_child("hypervisor")
                 .compatible_hafnium()
                 .start_child("vm1")
                 .debug_name("primary_vm")
+                .integer_property("id", 1)
                 .end_child()
                 .start_child("vm2")
                 .debug_name("secondary_vm")
-                .vcpu_count(vcpu_count)
+                .integer_property("id", id)
+                .vcpu_count(1)
                 .mem_size(0x1000)
                 .kernel_filename("kernel")
                 .end_child()
@@ -555,60 +6010,42 @@ mod test {
                 .build()
         }
 
-        let dtb_last_valid = gen_vcpu_count_limit_dtb(u16::max_value() as u64);
-        let dtb_first_invalid = gen_vcpu_count_limit_dtb(u16::max_value() as u64 + 1);
-
-        let fdt_root = get_fdt_root(&dtb_last_valid).unwrap();
+        let dtb = gen_vm_id_dtb(2);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
         let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
         m.init(&fdt_root).unwrap();
-        assert_eq!(m.vms.len(), 2);
-        assert_eq!(m.vms[1].vcpu_count, u16::max_value());
+        assert_eq!(m.vms[1].vm_id, 2);
 
-        let fdt_root = get_fdt_root(&dtb_first_invalid).unwrap();
-        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::IntegerOverflow);
+        let dtb = gen_vm_id_dtb(3);
+        let fdt_root = get_fdt_root(&dtb).unwrap();
+        assert_eq!(m.init(&fdt_root).unwrap_err(), Error::VmIdMismatch);
     }
 
     #[test]
-    fn valid() {
-        let dtb = ManifestDtBuilder::new()
+    fn count_by_exception_level() {
+        let mut builder = ManifestDtBuilder::new();
+        builder
             .start_child("hypervisor")
             .compatible_hafnium()
             .start_child("vm1")
             .debug_name("primary_vm")
-            .end_child()
-            .start_child("vm3")
-            .debug_name("second_secondary_vm")
-            .vcpu_count(43)
-            .mem_size(0x12345)
-            .kernel_filename("second_kernel")
-            .end_child()
-            .start_child("vm2")
-            .debug_name("first_secondary_vm")
-            .vcpu_count(42)
-            .mem_size(12345)
-            .kernel_filename("first_kernel")
-            .end_child()
-            .end_child()
-            .build();
+            .end_child();
+        for (i, exception_level) in [0, 1, 1].iter().enumerate() {
+            builder
+                .start_child(&format!("vm{}", i + 2))
+                .debug_name(&format!("partition{}", i + 2))
+                .vcpu_count(1)
+                .mem_size(0x1000)
+                .kernel_filename("kernel")
+                .boolean_property("secure")
+                .integer_property("exception-level", *exception_level)
+                .end_child();
+        }
+        let dtb = builder.end_child().build();
 
         let fdt_root = get_fdt_root(&dtb).unwrap();
         let mut m: Manifest = unsafe { MaybeUninit::uninit().assume_init() };
         m.init(&fdt_root).unwrap();
-        assert_eq!(m.vms.len(), 3);
-
-        let vm = &m.vms[0];
-        assert_eq!(as_asciz(&vm.debug_name), b"primary_vm");
-
-        let vm = &m.vms[1];
-        assert_eq!(as_asciz(&vm.debug_name), b"first_secondary_vm");
-        assert_eq!(vm.vcpu_count, 42);
-        assert_eq!(vm.mem_size, 12345);
-        assert_eq!(as_asciz(&vm.kernel_filename), b"first_kernel");
-
-        let vm = &m.vms[2];
-        assert_eq!(as_asciz(&vm.debug_name), b"second_secondary_vm");
-        assert_eq!(vm.vcpu_count, 43);
-        assert_eq!(vm.mem_size, 0x12345);
-        assert_eq!(as_asciz(&vm.kernel_filename), b"second_kernel");
+        assert_eq!(m.count_by_exception_level(), (1, 2));
     }
 }
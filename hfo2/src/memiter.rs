@@ -14,6 +14,8 @@
  * limitations under the License.
  */
 
+use core::fmt;
+use core::hash::Hasher;
 use core::ptr;
 use core::slice;
 use core::str;
@@ -21,8 +23,21 @@ use core::str;
 use crate::std::*;
 use crate::types::*;
 
+/// Reasons a `MemIter` operation can fail.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MemIterError {
+    /// The destination cannot hold the remaining bytes.
+    BufferTooSmall,
+    /// The remaining bytes cannot be split into chunks of the requested size.
+    UnevenChunks,
+    /// The remaining bytes are empty or contain a non-digit byte.
+    InvalidDigit,
+    /// The number does not fit in 64 bits.
+    Overflow,
+}
+
 #[repr(C)]
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct MemIter {
     next: *const u8,
     limit: *const u8,
@@ -64,6 +79,7 @@ impl MemIter {
     }
 
     /// Compares the iterator to a null-terminated string.
+    #[deprecated(note = "use `MemIter::matches`, which takes a `&str`")]
     pub unsafe fn iseq(&self, str: *const u8) -> bool {
         let self_len = self.limit as usize - self.next as usize;
         let len = strnlen_s(str, self_len + 1);
@@ -167,6 +183,104 @@ impl MemIter {
     pub fn len(&self) -> usize {
         unsafe { self.limit.offset_from(self.next) as usize }
     }
+
+    /// Copies the remaining bytes to the beginning of `dst`, and returns the number of bytes
+    /// copied.
+    pub fn copy_to(&self, dst: &mut [u8]) -> Result<usize, MemIterError> {
+        let src = unsafe { self.as_slice() };
+        let dst = dst
+            .get_mut(..src.len())
+            .ok_or(MemIterError::BufferTooSmall)?;
+        dst.copy_from_slice(src);
+
+        Ok(src.len())
+    }
+
+    /// Compares the remaining bytes to `s`.
+    pub fn matches(&self, s: &str) -> bool {
+        s.as_bytes() == unsafe { self.as_slice() }
+    }
+
+    /// Compares the remaining bytes to `other`, ignoring ASCII case.
+    pub fn eq_ignore_case(&self, other: &str) -> bool {
+        unsafe { self.as_slice() }.eq_ignore_ascii_case(other.as_bytes())
+    }
+
+    /// Splits the remaining bytes into consecutive chunks of `n` bytes each, e.g., the cells of a
+    /// property value. Fails if the bytes cannot be split evenly.
+    pub fn chunks(&self, n: usize) -> Result<MemIterChunks, MemIterError> {
+        if n == 0 || self.len() % n != 0 {
+            return Err(MemIterError::UnevenChunks);
+        }
+
+        Ok(MemIterChunks {
+            it: self.clone(),
+            size: n,
+        })
+    }
+
+    /// Parses all of the remaining bytes as an unsigned decimal number. Unlike `parse_uint`, does
+    /// not skip whitespace, and fails on empty input, non-digit bytes and overflow.
+    pub fn parse_u64_decimal(&self) -> Result<u64, MemIterError> {
+        let digits = unsafe { self.as_slice() };
+        if digits.is_empty() {
+            return Err(MemIterError::InvalidDigit);
+        }
+
+        digits.iter().try_fold(0u64, |value, &c| {
+            let digit = as_digit(c).ok_or(MemIterError::InvalidDigit)?;
+            value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(u64::from(digit)))
+                .ok_or(MemIterError::Overflow)
+        })
+    }
+
+    /// Folds the remaining bytes into an accumulator, starting from `init`, without advancing the
+    /// iterator.
+    pub fn fold_bytes<B, F: Fn(B, u8) -> B>(&self, init: B, f: F) -> B {
+        unsafe { self.as_slice() }
+            .iter()
+            .fold(init, |acc, &byte| f(acc, byte))
+    }
+
+    /// Feeds the remaining bytes of the iterator into the given hasher.
+    pub fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(unsafe { self.as_slice() });
+    }
+}
+
+/// Iterator over fixed-size chunks of a `MemIter`, created by `MemIter::chunks`.
+pub struct MemIterChunks {
+    it: MemIter,
+    size: usize,
+}
+
+impl Iterator for MemIterChunks {
+    type Item = MemIter;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.it.read(self.size)?;
+        Some(unsafe { MemIter::from_raw(next, self.size) })
+    }
+}
+
+/// Prints the remaining bytes as a string if they are valid UTF-8, or in hex otherwise.
+impl fmt::Debug for MemIter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = unsafe { self.as_slice() };
+
+        match str::from_utf8(bytes) {
+            Ok(s) => write!(f, "MemIter({:?})", s),
+            Err(_) => {
+                f.write_str("MemIter(")?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    write!(f, "{}{:02x}", if i == 0 { "" } else { " " }, byte)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
 }
 
 #[no_mangle]
@@ -179,7 +293,136 @@ pub unsafe extern "C" fn memiter_parse_str(it: *mut MemIter, str: *mut MemIter)
     (*it).parse_str().map(|s| *str = s).is_some()
 }
 
+/// C callers only have null-terminated strings, so this keeps using `MemIter::iseq`.
 #[no_mangle]
+#[allow(deprecated)]
 pub unsafe extern "C" fn memiter_iseq(it: *const MemIter, str: *const u8) -> bool {
     (*it).iseq(str)
 }
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::collections::hash_map::DefaultHasher;
+    use std::format;
+
+    use super::*;
+
+    fn hash_of(it: &MemIter) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        it.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn hash() {
+        let a = *b"primary_vm";
+        let b = *b"primary_vm";
+        let c = *b"secondary_vm";
+
+        let it_a = unsafe { MemIter::from_raw(a.as_ptr(), a.len()) };
+        let it_b = unsafe { MemIter::from_raw(b.as_ptr(), b.len()) };
+        let it_c = unsafe { MemIter::from_raw(c.as_ptr(), c.len()) };
+
+        assert_eq!(hash_of(&it_a), hash_of(&it_b));
+        assert_ne!(hash_of(&it_a), hash_of(&it_c));
+    }
+
+    #[test]
+    fn debug() {
+        let name = b"primary_vm";
+        let it = unsafe { MemIter::from_raw(name.as_ptr(), name.len()) };
+        assert_eq!(it.len(), name.len());
+        assert!(format!("{:?}", it).contains("primary_vm"));
+
+        let bytes = [0xde, 0xad, 0xbe, 0xef];
+        let it = unsafe { MemIter::from_raw(bytes.as_ptr(), bytes.len()) };
+        assert_eq!(format!("{:?}", it), "MemIter(de ad be ef)");
+    }
+
+    #[test]
+    fn eq_ignore_case() {
+        let name = b"Primary_VM";
+        let it = unsafe { MemIter::from_raw(name.as_ptr(), name.len()) };
+        assert!(it.eq_ignore_case("primary_vm"));
+        assert!(it.eq_ignore_case("PRIMARY_VM"));
+        assert!(!it.eq_ignore_case("primary_vm1"));
+        assert!(!it.eq_ignore_case("secondary_vm"));
+    }
+
+    #[test]
+    fn matches() {
+        let name = b"primary_vm";
+        let it = unsafe { MemIter::from_raw(name.as_ptr(), name.len()) };
+        assert!(it.matches("primary_vm"));
+        assert!(!it.matches("Primary_VM"));
+        assert!(!it.matches("primary_vm\0"));
+        assert!(!it.matches("primary"));
+
+        let empty = unsafe { MemIter::from_raw(name.as_ptr(), 0) };
+        assert!(empty.matches(""));
+        assert!(!empty.matches("primary_vm"));
+    }
+
+    #[test]
+    fn chunks() {
+        let cells = [0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4];
+        let it = unsafe { MemIter::from_raw(cells.as_ptr(), cells.len()) };
+
+        let mut chunks = it.chunks(4).unwrap();
+        for i in 1..=4 {
+            let chunk = chunks.next().unwrap();
+            assert_eq!(unsafe { chunk.as_slice() }, &[0, 0, 0, i]);
+        }
+        assert!(chunks.next().is_none());
+        assert_eq!(it.len(), cells.len());
+
+        assert_eq!(it.chunks(3).err(), Some(MemIterError::UnevenChunks));
+        assert_eq!(it.chunks(0).err(), Some(MemIterError::UnevenChunks));
+    }
+
+    #[test]
+    fn parse_u64_decimal() {
+        let parse = |s: &str| unsafe { MemIter::from_raw(s.as_ptr(), s.len()) }.parse_u64_decimal();
+
+        assert_eq!(parse("12345"), Ok(12345));
+        assert_eq!(parse("18446744073709551615"), Ok(u64::max_value()));
+        assert_eq!(parse(""), Err(MemIterError::InvalidDigit));
+        assert_eq!(parse("12a"), Err(MemIterError::InvalidDigit));
+        assert_eq!(parse(" 1"), Err(MemIterError::InvalidDigit));
+        assert_eq!(parse("18446744073709551616"), Err(MemIterError::Overflow));
+    }
+
+    #[test]
+    fn fold_bytes() {
+        let bytes = [1, 2, 3, 0xff];
+        let it = unsafe { MemIter::from_raw(bytes.as_ptr(), bytes.len()) };
+        assert_eq!(it.fold_bytes(0u32, |sum, byte| sum + u32::from(byte)), 261);
+        assert_eq!(it.fold_bytes(0u8, |parity, byte| parity ^ byte), 0xff);
+        assert_eq!(it.len(), bytes.len());
+
+        let empty = unsafe { MemIter::from_raw(bytes.as_ptr(), 0) };
+        assert_eq!(empty.fold_bytes(7, |sum, byte| sum + byte), 7);
+    }
+
+    #[test]
+    fn copy_to() {
+        let name = b"primary_vm";
+        let it = unsafe { MemIter::from_raw(name.as_ptr(), name.len()) };
+
+        let mut exact = [0; 10];
+        assert_eq!(it.copy_to(&mut exact), Ok(10));
+        assert_eq!(&exact, name);
+
+        let mut oversized = [0xff; 16];
+        assert_eq!(it.copy_to(&mut oversized), Ok(10));
+        assert_eq!(&oversized[..10], name);
+        assert_eq!(&oversized[10..], &[0xff; 6]);
+
+        let mut too_small = [0; 9];
+        assert_eq!(
+            it.copy_to(&mut too_small),
+            Err(MemIterError::BufferTooSmall)
+        );
+    }
+}
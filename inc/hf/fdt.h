@@ -25,6 +25,7 @@ struct fdt_node {
 	const char *begin;
 	const char *end;
 	const char *strs;
+	size_t depth;
 };
 
 bool fdt_root_node(struct fdt_node *node, const struct fdt_header *hdr);
@@ -62,6 +62,7 @@ $TIMEOUT 30s $OUT/host_fake_clang/unit_tests \
   | tee $OUT/kokoro_log/unit_tests/sponge_log.log
 
 RUSTFLAGS="-L ../$OUT/host_fake_clang/obj/src -C link-arg=-no-pie" cargo test --manifest-path=hfo2/Cargo.toml
+RUSTFLAGS="-L ../$OUT/host_fake_clang/obj/src -C link-arg=-no-pie" cargo test --manifest-path=hfo2/Cargo.toml --features stats
 
 $HFTEST arch_test
 $HFTEST hafnium --initrd test/vmapi/gicv3/gicv3_test